@@ -0,0 +1,18 @@
+-- Add migration script here
+/**
+@description
+Optional per-tenant connection pool settings.
+A `NULL` value falls back to the global `database` configuration of the application.
+- `pool_acquire_timeout` is expressed in milliseconds;
+- `pool_max_lifetime` and `pool_idle_timeout` are expressed in seconds;
+*/
+ALTER TABLE tenants
+    ADD COLUMN IF NOT EXISTS pool_min_connections INTEGER CHECK (pool_min_connections >= 0),
+    ADD COLUMN IF NOT EXISTS pool_max_connections INTEGER CHECK (pool_max_connections > 0),
+    ADD COLUMN IF NOT EXISTS pool_acquire_timeout BIGINT CHECK (pool_acquire_timeout > 0),
+    ADD COLUMN IF NOT EXISTS pool_max_lifetime BIGINT CHECK (pool_max_lifetime > 0),
+    ADD COLUMN IF NOT EXISTS pool_idle_timeout BIGINT CHECK (pool_idle_timeout > 0);
+
+ALTER TABLE tenants
+    ADD CONSTRAINT tenants_pool_connections_check
+        CHECK (pool_min_connections IS NULL OR pool_max_connections IS NULL OR pool_min_connections <= pool_max_connections);
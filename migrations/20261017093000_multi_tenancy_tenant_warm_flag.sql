@@ -0,0 +1,8 @@
+-- Add migration script here
+/**
+@description
+Tenants flagged as `warm` get their connection pool created at application startup
+and are never evicted for being idle.
+*/
+ALTER TABLE tenants
+    ADD COLUMN IF NOT EXISTS warm BOOLEAN NOT NULL DEFAULT FALSE;
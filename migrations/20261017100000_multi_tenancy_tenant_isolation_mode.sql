@@ -0,0 +1,23 @@
+-- Add migration script here
+/**
+@description
+Lets a tenant live outside of the shared, row level security protected database.
+- `shared`: the tenant's rows live in the application database and are isolated by RLS (default);
+- `database`: the tenant has its own database, optionally on its own host/cluster.
+  `NULL` connection columns fall back to the application database configuration;
+*/
+ALTER TABLE tenants
+    ADD COLUMN IF NOT EXISTS isolation_mode VARCHAR(32) NOT NULL DEFAULT 'shared',
+    ADD COLUMN IF NOT EXISTS db_host VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS db_port INTEGER CHECK (db_port > 0 AND db_port < 65536),
+    ADD COLUMN IF NOT EXISTS db_name VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS db_ssl_mode VARCHAR(32)
+        CHECK (db_ssl_mode IN ('disable', 'allow', 'prefer', 'require', 'verify-ca', 'verify-full'));
+
+ALTER TABLE tenants
+    ADD CONSTRAINT tenants_isolation_mode_check
+        CHECK (isolation_mode IN ('shared', 'database'));
+
+ALTER TABLE tenants
+    ADD CONSTRAINT tenants_dedicated_database_check
+        CHECK (isolation_mode <> 'database' OR db_name IS NOT NULL);
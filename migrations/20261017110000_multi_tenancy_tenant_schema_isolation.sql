@@ -0,0 +1,21 @@
+-- Add migration script here
+/**
+@description
+Adds the `schema` isolation mode: the tenant's tables live in a Postgres schema of its own,
+within the application database. Tenant connections point their `search_path` at `db_schema`.
+*/
+ALTER TABLE tenants
+    ADD COLUMN IF NOT EXISTS db_schema VARCHAR(63);
+
+ALTER TABLE tenants
+    DROP CONSTRAINT IF EXISTS tenants_isolation_mode_check;
+
+ALTER TABLE tenants
+    ADD CONSTRAINT tenants_isolation_mode_check
+        CHECK (isolation_mode IN ('shared', 'schema', 'database'));
+
+ALTER TABLE tenants
+    ADD CONSTRAINT tenants_dedicated_schema_check
+        CHECK (isolation_mode <> 'schema' OR db_schema IS NOT NULL);
+
+CREATE UNIQUE INDEX IF NOT EXISTS tenants_db_schema_unique_idx ON tenants (db_schema);
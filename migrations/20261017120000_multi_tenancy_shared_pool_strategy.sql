@@ -0,0 +1,168 @@
+-- Add migration script here
+/**
+@private
+@description
+- Same as `add_current_user_tenant_rls_policy(..)`, but the tenant is read from the `app.current_tenant` setting
+  instead of being derived from `current_user`;
+- Meant for the shared pool strategy, where a single `tenant_switcher` login runs `SET LOCAL ROLE tenant_shared`
+  and `set_config('app.current_tenant', <tenant id>, true)` at the start of every transaction;
+- The policy only applies to `tenant_role`, never to `tenant_base`: any role can set a custom setting,
+  so tenant roles must not be able to widen their visibility through it;
+*/
+CREATE OR REPLACE FUNCTION add_current_setting_tenant_rls_policy(table_name TEXT, tenant_role TEXT) RETURNS void AS $$
+DECLARE
+    policy_name TEXT;
+BEGIN
+    policy_name := 'tenant_setting_isolation_policy_' || table_name;
+
+    EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', table_name);
+
+    EXECUTE format('DROP POLICY IF EXISTS %I ON %I', policy_name, table_name);
+
+    EXECUTE format('
+    CREATE POLICY %I ON %I
+    FOR ALL
+    TO %I
+    USING (
+        tenant_id = NULLIF(current_setting(''app.current_tenant'', true), '''')::UUID
+    )
+    WITH CHECK (
+        tenant_id = NULLIF(current_setting(''app.current_tenant'', true), '''')::UUID
+    )
+		', policy_name, table_name, tenant_role);
+
+    RAISE NOTICE 'Tenant setting isolation policy applied to table: %', table_name;
+EXCEPTION
+    WHEN undefined_column THEN
+        RAISE EXCEPTION 'Table % does not have a tenant_id column', table_name;
+    WHEN undefined_table THEN
+        RAISE EXCEPTION 'Table % does not exist', table_name;
+    WHEN OTHERS THEN
+        RAISE EXCEPTION 'Error applying tenant setting isolation policy to table %: %', table_name, SQLERRM;
+END;
+$$ LANGUAGE plpgsql;
+
+/**
+@private
+@description
+- Looks up the matching tenant id against the `current_user` (of the connected session pool user);
+- Falls back to the `app.current_tenant` setting (shared pool strategy), the RLS policies still check the result;
+*/
+CREATE OR REPLACE FUNCTION set_tenant_id() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE NOTICE 'set_tenant_id trigger fired for table: %, operation: %', TG_TABLE_NAME, TG_OP;
+    RAISE NOTICE 'Current user: %, Current role: %', current_user, current_setting('role');
+
+    IF NEW.tenant_id IS NULL THEN
+        SELECT id INTO NEW.tenant_id
+        FROM tenants
+        WHERE db_user = current_user;
+
+        IF NEW.tenant_id IS NULL THEN
+            NEW.tenant_id := NULLIF(current_setting('app.current_tenant', true), '')::UUID;
+        END IF;
+
+        IF NEW.tenant_id IS NULL THEN
+            RAISE EXCEPTION 'No tenant found for user: %', current_user;
+        END IF;
+
+        RAISE NOTICE 'Setting tenant_id to % for user %', NEW.tenant_id, current_user;
+    ELSE
+        RAISE NOTICE 'Tenant ID already set: %', NEW.tenant_id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+/**
+@private
+@description
+- Creates the `switcher_role` (NOINHERIT, so it has no table privileges of its own) if it doesn't exist;
+- Makes it a member of `tenant_shared_role` and of every existing tenant role so that it can `SET ROLE` to them;
+- The role is created without LOGIN, give it a password out of band:
+  ALTER ROLE tenant_switcher WITH LOGIN PASSWORD '...';
+
+@usage
+SELECT setup_tenant_switcher_role('tenant_switcher', 'tenant_shared', 'tenant_base');
+*/
+CREATE OR REPLACE FUNCTION setup_tenant_switcher_role(switcher_role TEXT, tenant_shared_role TEXT, tenant_base_role TEXT) RETURNS void AS $$
+DECLARE
+    tenant_role TEXT;
+BEGIN
+    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = switcher_role) THEN
+        EXECUTE format('CREATE ROLE %I NOINHERIT', switcher_role);
+        RAISE NOTICE 'Created new role: %', switcher_role;
+    END IF;
+
+    EXECUTE format('GRANT %I TO %I', tenant_shared_role, switcher_role);
+
+    FOR tenant_role IN (
+        SELECT r.rolname
+        FROM pg_auth_members m
+                 JOIN pg_roles b ON (m.roleid = b.oid)
+                 JOIN pg_roles r ON (m.member = r.oid)
+        WHERE b.rolname = tenant_base_role
+    )
+        LOOP
+            EXECUTE format('GRANT %I TO %I', tenant_role, switcher_role);
+        END LOOP;
+
+    RAISE NOTICE 'Setup completed successfully for switcher role: %', switcher_role;
+END;
+$$ LANGUAGE plpgsql;
+
+/*
+@private
+@description:
+Same as before, new tenant roles are also granted to the `tenant_switcher` role (when it exists),
+so that the shared pool strategy can `SET ROLE` to them.
+*/
+CREATE OR REPLACE FUNCTION upsert_tenant_role(tenant_base_role TEXT, role_name TEXT, db_password_plaintext TEXT) RETURNS void AS $$
+BEGIN
+    IF EXISTS (SELECT FROM pg_roles WHERE rolname = role_name) THEN
+        -- Role exists, update it
+        EXECUTE format('ALTER ROLE %I WITH LOGIN PASSWORD %L', role_name, db_password_plaintext);
+
+        -- Ensure it inherits from `tenant_base_role`
+        IF NOT EXISTS (
+            SELECT FROM pg_auth_members m
+                            JOIN pg_roles r ON (m.roleid = r.oid)
+            WHERE r.rolname = tenant_base_role AND m.member = (SELECT oid FROM pg_roles WHERE rolname = role_name)
+        ) THEN
+            EXECUTE format('GRANT %I TO %I', tenant_base_role, role_name);
+        END IF;
+
+        RAISE NOTICE 'Role % updated', role_name;
+    ELSE
+        -- Role doesn't exist, create it
+        EXECUTE format('CREATE ROLE %I WITH LOGIN PASSWORD %L IN ROLE %I', role_name, db_password_plaintext, tenant_base_role);
+        RAISE NOTICE 'Role % created', role_name;
+    END IF;
+
+    IF EXISTS (SELECT FROM pg_roles WHERE rolname = 'tenant_switcher') THEN
+        EXECUTE format('GRANT %I TO %I', role_name, 'tenant_switcher');
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+    DECLARE
+        tenant_base_role TEXT := 'tenant_base';
+        tenant_shared_role TEXT := 'tenant_shared';
+        tenant_switcher_role TEXT := 'tenant_switcher';
+        security_policy_impacted_tables TEXT[] := ARRAY['users'];
+        current_table TEXT;
+    BEGIN
+        PERFORM setup_tenant_base_role(tenant_shared_role);
+
+        FOREACH current_table IN ARRAY security_policy_impacted_tables
+            LOOP
+                PERFORM add_current_setting_tenant_rls_policy(current_table, tenant_shared_role);
+            END LOOP;
+
+        PERFORM setup_tenant_switcher_role(tenant_switcher_role, tenant_shared_role, tenant_base_role);
+    EXCEPTION
+        WHEN OTHERS THEN
+            RAISE NOTICE 'An error occurred: % %', SQLERRM, SQLSTATE;
+    END $$;
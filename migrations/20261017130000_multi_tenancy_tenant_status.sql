@@ -0,0 +1,20 @@
+-- Add migration script here
+/**
+@description
+Tenant lifecycle: `active` -> `suspended` -> `active` again, or `pending_deletion` which
+can still be restored until its grace period ends. The deletion job then marks the tenant
+as `deleted`, drops its role and deletes the row, cascading to its `tenant_id` rows.
+
+`status_changed_at` is tracked separately from `updated_at`, which dates the credentials.
+*/
+ALTER TABLE tenants
+    ADD COLUMN IF NOT EXISTS status VARCHAR(32) NOT NULL DEFAULT 'active',
+    ADD COLUMN IF NOT EXISTS status_changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+ALTER TABLE tenants
+    ADD CONSTRAINT tenants_status_check
+        CHECK (status IN ('active', 'suspended', 'pending_deletion', 'deleted'));
+
+CREATE INDEX IF NOT EXISTS tenants_pending_deletion_idx
+    ON tenants (status_changed_at)
+    WHERE status IN ('pending_deletion', 'deleted');
@@ -0,0 +1,97 @@
+-- Add migration script here
+/**
+@private
+@description
+Audit trail of offboarded tenants, one row per `delete_tenant_with_role(..)` call.
+Tenant roles have no access to it.
+*/
+CREATE TABLE IF NOT EXISTS tenant_deletions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL,
+    tenant_name VARCHAR(255) NOT NULL,
+    db_user VARCHAR(255) NOT NULL,
+    terminated_backends INTEGER NOT NULL,
+    deleted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+DO $$
+DECLARE
+    base_role TEXT;
+BEGIN
+    FOREACH base_role IN ARRAY ARRAY['tenant_base', 'tenant_shared']
+        LOOP
+            IF EXISTS (SELECT FROM pg_roles WHERE rolname = base_role) THEN
+                EXECUTE format('REVOKE ALL PRIVILEGES ON TABLE tenant_deletions FROM %I', base_role);
+            END IF;
+        END LOOP;
+END $$;
+
+/**
+@public
+@description
+Inverse of `create_tenant_with_role(..)`, offboards a tenant in a single transaction:
+- The tenant's role loses its login and its open sessions are terminated with `pg_terminate_backend`;
+- Its memberships (`tenant_base_role`, `tenant_switcher`) are revoked, its privileges revoked and the objects
+  it owns in this database (e.g. its schema) dropped with `DROP OWNED BY`;
+- The `tenants` row is deleted, cascading to every row carrying its `tenant_id`, and the role is dropped;
+- A `tenant_deletions` row records the operation;
+
+Roles still owning a dedicated database can't be dropped, the whole operation then fails and nothing changes.
+
+@usage
+Called from the application layer:
+SELECT * FROM delete_tenant_with_role('tenant_base', 'existing_tenant');
+
+@returns
+Returns the `tenant_deletions` row, or no row if the tenant was not found.
+*/
+CREATE OR REPLACE FUNCTION delete_tenant_with_role(tenant_base_role TEXT, tenant_name TEXT) RETURNS SETOF tenant_deletions AS $$
+DECLARE
+    deleted_tenant_id UUID;
+    role_name TEXT;
+    terminated_backends INTEGER := 0;
+    backend_pid INTEGER;
+    deletion tenant_deletions;
+BEGIN
+    SELECT id, db_user INTO deleted_tenant_id, role_name
+    FROM tenants
+    WHERE name = tenant_name
+    FOR UPDATE;
+
+    IF deleted_tenant_id IS NULL THEN
+        RAISE NOTICE 'Tenant % not found', tenant_name;
+        RETURN;
+    END IF;
+
+    IF EXISTS (SELECT FROM pg_roles WHERE rolname = role_name) THEN
+        -- No new sessions from here on, then end the open ones
+        EXECUTE format('ALTER ROLE %I WITH NOLOGIN', role_name);
+
+        -- A loop rather than a filter, so that the planner never terminates this very backend
+        FOR backend_pid IN
+            SELECT pid FROM pg_stat_activity WHERE usename = role_name AND pid <> pg_backend_pid()
+            LOOP
+                IF pg_terminate_backend(backend_pid) THEN
+                    terminated_backends := terminated_backends + 1;
+                END IF;
+            END LOOP;
+
+        EXECUTE format('REVOKE %I FROM %I', tenant_base_role, role_name);
+        IF EXISTS (SELECT FROM pg_roles WHERE rolname = 'tenant_switcher') THEN
+            EXECUTE format('REVOKE %I FROM tenant_switcher', role_name);
+        END IF;
+        EXECUTE format('DROP OWNED BY %I', role_name);
+    END IF;
+
+    DELETE FROM tenants WHERE id = deleted_tenant_id;
+
+    EXECUTE format('DROP ROLE IF EXISTS %I', role_name);
+
+    INSERT INTO tenant_deletions (tenant_id, tenant_name, db_user, terminated_backends)
+    VALUES (deleted_tenant_id, tenant_name, role_name, terminated_backends)
+    RETURNING * INTO deletion;
+
+    RAISE NOTICE 'Deleted tenant % and role %', tenant_name, role_name;
+    RETURN NEXT deletion;
+END;
+$$ LANGUAGE plpgsql;
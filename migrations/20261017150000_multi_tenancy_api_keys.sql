@@ -0,0 +1,32 @@
+-- Add migration script here
+/**
+@private
+@description
+Tenant scoped API keys of machine clients. Only a hash of the secret part of a key is kept,
+`prefix` identifies the key without revealing it. Tenant roles have no access to it.
+*/
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    prefix VARCHAR(32) NOT NULL UNIQUE,
+    key_hash VARCHAR(255) NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_tenant_id_idx ON api_keys (tenant_id);
+
+DO $$
+DECLARE
+    base_role TEXT;
+BEGIN
+    FOREACH base_role IN ARRAY ARRAY['tenant_base', 'tenant_shared']
+        LOOP
+            IF EXISTS (SELECT FROM pg_roles WHERE rolname = base_role) THEN
+                EXECUTE format('REVOKE ALL PRIVILEGES ON TABLE api_keys FROM %I', base_role);
+            END IF;
+        END LOOP;
+END $$;
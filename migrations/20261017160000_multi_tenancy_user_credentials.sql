@@ -0,0 +1,11 @@
+-- Add migration script here
+/**
+@description
+Login credentials of end users. `password_hash` is an Argon2id PHC string, users created
+without a password can't log in. Emails are unique within a tenant.
+*/
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS email VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS password_hash VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_tenant_id_email_key ON users (tenant_id, email);
@@ -0,0 +1,14 @@
+-- Add migration script here
+/**
+@description
+Tenant scoped schema, applied to every dedicated tenant database.
+There is no `tenant_id` column nor RLS policy here: the whole database belongs to a single tenant.
+*/
+CREATE TABLE IF NOT EXISTS users (
+     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+     first_name VARCHAR(255) NOT NULL,
+     last_name VARCHAR(255) NOT NULL,
+     confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+     created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+     updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+/**
+@description
+Login credentials of end users, see the application migration of the same name.
+*/
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS email VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS password_hash VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_key ON users (email);
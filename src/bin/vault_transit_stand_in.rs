@@ -0,0 +1,44 @@
+//! Runs [`VaultTransitStandIn`] for the `vault_transit` secret provider, e.g.
+//!
+//! ```sh
+//! VAULT_TRANSIT_STAND_IN_TOKEN=dev-token VAULT_TRANSIT_STAND_IN_KEY=$(openssl rand -hex 32) \
+//!     cargo run --features vault-transit-stand-in --bin vault-transit-stand-in
+//! ```
+//!
+//! Secrets are then wrapped with its encrypt endpoint, as they would be with Vault.
+
+use rust_actix_postgres_multi_tenant::utils::VaultTransitStandIn;
+use secrecy::SecretString;
+use std::env;
+use std::net::TcpListener;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let address =
+        env::var("VAULT_TRANSIT_STAND_IN_ADDRESS").unwrap_or_else(|_| "127.0.0.1:8200".into());
+    let mount = env::var("VAULT_TRANSIT_STAND_IN_MOUNT").unwrap_or_else(|_| "transit".into());
+    let key_name = env::var("VAULT_TRANSIT_STAND_IN_KEY_NAME")
+        .unwrap_or_else(|_| "rust-actix-postgres-multi-tenant".into());
+    let token = env::var("VAULT_TRANSIT_STAND_IN_TOKEN")
+        .expect("VAULT_TRANSIT_STAND_IN_TOKEN is required.");
+    let key_hex =
+        env::var("VAULT_TRANSIT_STAND_IN_KEY").expect("VAULT_TRANSIT_STAND_IN_KEY is required.");
+
+    let (stand_in, server) = VaultTransitStandIn::start(
+        TcpListener::bind(&address)?,
+        &mount,
+        &key_name,
+        SecretString::from(token),
+        SecretString::from(key_hex),
+    )?;
+    println!(
+        "Serving /v1/{}/encrypt/{} and /v1/{}/decrypt/{} on {}",
+        mount,
+        key_name,
+        mount,
+        key_name,
+        stand_in.address()
+    );
+
+    server.await
+}
@@ -1,4 +1,8 @@
-use crate::deserializers::{deserialize_number_from_string, deserialize_option_number_from_string};
+use crate::deserializers::{
+    deserialize_list_from_string, deserialize_number_from_string,
+    deserialize_option_number_from_string,
+};
+use crate::utils::AesGcmCipher;
 use actix_session::config::CookieContentSecurity;
 use actix_web::cookie::SameSite;
 use config::{Config, ConfigError, File};
@@ -6,7 +10,10 @@ use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use sqlx::ConnectOptions;
+use std::collections::HashMap;
 use std::env;
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
 
 pub fn get_current_environment() -> Environment {
     let environment: Environment = env::var("APP_ENVIRONMENT")
@@ -36,7 +43,14 @@ pub fn get_configuration() -> Result<Configuration, ConfigError> {
         )
         .build()?;
 
-    configurations.try_deserialize::<Configuration>()
+    let configuration = configurations.try_deserialize::<Configuration>()?;
+    // Other providers replace the configured values at startup, see `resolve_secrets(..)`
+    configuration
+        .secrets
+        .store
+        .replace(Secrets::try_from(&configuration.secrets).map_err(ConfigError::Message)?);
+
+    Ok(configuration)
 }
 
 #[derive(Deserialize, Clone)]
@@ -44,7 +58,10 @@ pub struct Configuration {
     pub debug: bool,
     pub application: ApplicationConfiguration,
     pub database: DatabaseConfiguration,
+    pub tenant_pools: TenantPoolsConfiguration,
+    pub tenant_resolution: TenantResolutionConfiguration,
     pub redis: RedisConfiguration,
+    pub internal_network: InternalNetworkConfiguration,
     pub secrets: SecretsConfiguration,
     pub frontend_url: Option<String>,
 }
@@ -55,6 +72,20 @@ pub struct ApplicationConfiguration {
     pub host: String,
     pub cookie: CookieConfiguration,
     pub certificate: Option<CertificateConfiguration>,
+
+    #[serde(default)]
+    pub session_store: SessionStoreBackend,
+}
+
+/// Where session state is kept.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStoreBackend {
+    /// In the session cookie itself, sessions can't be revoked before they expire.
+    #[default]
+    Cookie,
+    /// In Redis (`redis`), the cookie only holds the session key.
+    Redis,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -137,6 +168,169 @@ pub struct DatabaseConfiguration {
     pub idle_timeout: Option<u64>,
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct TenantPoolsConfiguration {
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_pools: Option<usize>,
+
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_total_connections: Option<u32>,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retry_after_in_seconds: u64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub drain_timeout_in_seconds: u64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub janitor_interval_in_seconds: u64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub idle_threshold_in_seconds: u64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub probe_idle_threshold_in_seconds: u64,
+
+    /// How long tenants stay `pending_deletion`, and can be restored, before being hard deleted.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub deletion_grace_period_in_seconds: u64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub deletion_interval_in_seconds: u64,
+
+    /// Tenants whose pools are created at startup and never evicted for being idle,
+    /// in addition to the ones flagged as `warm` in the `tenants` table.
+    #[serde(default)]
+    pub warm_tenants: Vec<Uuid>,
+
+    #[serde(default)]
+    pub strategy: TenantPoolStrategy,
+
+    /// Login used by the shared pool strategies, required unless `strategy` is `per_tenant`.
+    #[serde(default)]
+    pub shared_pool: Option<SharedTenantPoolConfiguration>,
+
+    /// Scheduled rotation of tenant passwords, disabled when unset.
+    #[serde(default)]
+    pub credentials_rotation: Option<TenantCredentialsRotationConfiguration>,
+}
+
+/// How connections are handed out to tenants living in the shared database.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantPoolStrategy {
+    /// A pool per tenant, logged in with the tenant's own role.
+    #[default]
+    PerTenant,
+    /// A single pool logged in as the switcher role, each transaction runs `SET LOCAL ROLE <tenant role>`.
+    SharedSetRole,
+    /// A single pool logged in as the switcher role, each transaction runs `SET LOCAL ROLE tenant_shared`
+    /// and sets `app.current_tenant`.
+    SharedTenantSetting,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SharedTenantPoolConfiguration {
+    pub username: String,
+    pub password: SecretString,
+
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_connections: Option<u32>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TenantCredentialsRotationConfiguration {
+    /// Passwords are rotated once `tenants.updated_at` is older than this.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_age_in_seconds: u64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub interval_in_seconds: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TenantResolutionConfiguration {
+    /// Tried in order, the first strategy finding a tenant in the request wins.
+    #[serde(deserialize_with = "deserialize_list_from_string")]
+    pub strategies: Vec<TenantResolutionStrategy>,
+
+    pub header_name: String,
+
+    /// Domain tenant subdomains live under, required by the `subdomain` strategy.
+    #[serde(default)]
+    pub base_domain: Option<String>,
+
+    /// Routes are also mounted under `<path_prefix>/{tenant}` when the `path` strategy is used.
+    pub path_prefix: String,
+
+    pub session_key: String,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub name_cache_ttl_in_seconds: u64,
+
+    /// Verification of bearer tokens, required by the `jwt` strategy.
+    #[serde(default)]
+    pub jwt: Option<JwtTenantResolutionConfiguration>,
+}
+
+/// Where in a request the tenant is looked up.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantResolutionStrategy {
+    /// Tenant of the API key verified for the request, see `ApiKeyAuthentication`.
+    ApiKey,
+    /// Tenant id or name in a header, `x-tenant-id` by default.
+    Header,
+    /// Tenant name as the leftmost label of the host, e.g. `acme.example.com`.
+    Subdomain,
+    /// Tenant id or name as the first path segment after the prefix, e.g. `/t/acme/v1/products`.
+    Path,
+    /// Tenant id or name in a claim of an HS256 signed bearer token.
+    Jwt,
+    /// Tenant id or name stored in the actix session.
+    Session,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct JwtTenantResolutionConfiguration {
+    pub secret: SecretString,
+    pub claim: String,
+    #[serde(default)]
+    pub issuer: Option<String>,
+    #[serde(default)]
+    pub audience: Option<String>,
+}
+
+/// Who may call the `/internal` routes, see `InternalNetworkGuard`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct InternalNetworkConfiguration {
+    /// Networks in CIDR notation (`10.0.0.0/8`, `fd00::/8`) or single addresses.
+    #[serde(deserialize_with = "deserialize_list_from_string")]
+    pub trusted_networks: Vec<String>,
+
+    /// Reverse proxies whose `forwarded_header` is honored, requests from anywhere else
+    /// are judged by their peer address alone.
+    #[serde(default, deserialize_with = "deserialize_list_from_string")]
+    pub trusted_proxies: Vec<String>,
+
+    #[serde(default)]
+    pub forwarded_header: ForwardedHeader,
+
+    /// Marking a request as internal, only honored when it comes from a trusted proxy.
+    #[serde(default, deserialize_with = "deserialize_list_from_string")]
+    pub trusted_headers: Vec<String>,
+}
+
+/// The one header the trusted proxies append the client to, the other one is ignored
+/// as they pass it through from the client unchanged.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForwardedHeader {
+    #[default]
+    XForwardedFor,
+    Forwarded,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct RedisConfiguration {
     pub username: Option<String>,
@@ -158,7 +352,220 @@ pub struct SecretsConfiguration {
     pub argon2_key: SecretString,
 
     pub hmac: SecretString,
+    /// Decrypts ciphertexts written before they named their key. It is also the key with the
+    /// id `default` unless the keyring holds one.
     pub aes256_gcm_key: SecretString,
+
+    #[serde(default)]
+    pub keyring: KeyringConfiguration,
+
+    /// Whether ciphertexts written before they were bound to their tenant still decrypt.
+    #[serde(default)]
+    pub accept_unbound_ciphertexts: bool,
+
+    /// Where the secrets above are resolved, see [`crate::utils::SecretProvider`].
+    #[serde(default)]
+    pub provider: SecretProviderKind,
+
+    /// How often secrets are resolved again, only at startup when empty.
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub refresh_interval_in_seconds: Option<u64>,
+
+    /// Required by the `file` provider.
+    pub file: Option<FileSecretsConfiguration>,
+
+    /// Required by the `vault_transit` provider.
+    pub vault_transit: Option<VaultTransitConfiguration>,
+
+    /// The resolved secrets, shared by every clone of the configuration.
+    #[serde(skip)]
+    pub store: SecretStore,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretProviderKind {
+    /// The configured values themselves, from the configuration files or `APP_SECRETS__*`.
+    #[default]
+    Config,
+    /// Files named after each secret, e.g. Docker or Kubernetes secrets.
+    File,
+    /// Configured values are ciphertexts of a Vault transit key, decrypted by Vault.
+    VaultTransit,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct FileSecretsConfiguration {
+    /// Secrets are read from `<directory>/<name>`, e.g. `/run/secrets/hmac`.
+    pub directory: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct VaultTransitConfiguration {
+    /// E.g. `http://127.0.0.1:8200`.
+    pub address: String,
+    pub token: SecretString,
+    #[serde(default = "default_vault_transit_mount")]
+    pub mount: String,
+    pub key_name: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub timeout_in_seconds: u64,
+}
+
+fn default_vault_transit_mount() -> String {
+    "transit".to_string()
+}
+
+pub const DEFAULT_ENCRYPTION_KEY_ID: &str = "default";
+
+/// Hex encoded AES-256-GCM keys by id. New ciphertexts are encrypted with the active key,
+/// the others only decrypt what was written with them until it is re-encrypted.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct KeyringConfiguration {
+    /// Falls back to `default` when empty.
+    #[serde(default)]
+    pub active_key_id: String,
+    #[serde(default)]
+    pub keys: HashMap<String, SecretString>,
+}
+
+impl SecretsConfiguration {
+    /// The secrets as last resolved by the configured provider.
+    pub fn current(&self) -> Arc<Secrets> {
+        self.store.current()
+    }
+}
+
+/// The secrets of [`SecretsConfiguration`] as resolved by its provider.
+#[derive(Clone, Debug, Default)]
+pub struct Secrets {
+    pub argon2_salt: SecretString,
+    pub argon2_key: SecretString,
+    pub hmac: SecretString,
+    pub aes256_gcm_key: SecretString,
+    pub keyring: KeyringConfiguration,
+
+    /// `aes256_gcm_key` and the keyring keys, decoded once.
+    unversioned_cipher: Option<AesGcmCipher>,
+    ciphers: HashMap<String, AesGcmCipher>,
+}
+
+impl TryFrom<&SecretsConfiguration> for Secrets {
+    type Error = String;
+
+    /// The configured values as they are.
+    fn try_from(configuration: &SecretsConfiguration) -> Result<Self, Self::Error> {
+        Secrets::new(
+            configuration.argon2_salt.clone(),
+            configuration.argon2_key.clone(),
+            configuration.hmac.clone(),
+            configuration.aes256_gcm_key.clone(),
+            configuration.keyring.clone(),
+        )
+    }
+}
+
+impl Secrets {
+    /// Decodes the encryption keys, the ones left empty are left out.
+    pub fn new(
+        argon2_salt: SecretString,
+        argon2_key: SecretString,
+        hmac: SecretString,
+        aes256_gcm_key: SecretString,
+        keyring: KeyringConfiguration,
+    ) -> Result<Self, String> {
+        let decode = |name: &str, key: &SecretString| match key.expose_secret() {
+            "" => Ok(None),
+            _ => AesGcmCipher::from_hex(key)
+                .map(Some)
+                .map_err(|e| format!("{}: {}", name, e)),
+        };
+
+        let unversioned_cipher = decode("secrets.aes256_gcm_key", &aes256_gcm_key)?;
+        let mut ciphers = HashMap::with_capacity(keyring.keys.len() + 1);
+        for (key_id, key) in &keyring.keys {
+            if let Some(cipher) = decode(&format!("secrets.keyring.keys.{}", key_id), key)? {
+                ciphers.insert(key_id.clone(), cipher);
+            }
+        }
+        if let Some(cipher) = &unversioned_cipher {
+            ciphers
+                .entry(DEFAULT_ENCRYPTION_KEY_ID.to_string())
+                .or_insert_with(|| cipher.clone());
+        }
+
+        Ok(Secrets {
+            argon2_salt,
+            argon2_key,
+            hmac,
+            aes256_gcm_key,
+            keyring,
+            unversioned_cipher,
+            ciphers,
+        })
+    }
+
+    /// The id of the key new ciphertexts are encrypted with, and the key itself.
+    pub fn active_encryption_key(&self) -> Result<(&str, &AesGcmCipher), String> {
+        let key_id = match self.keyring.active_key_id.as_str() {
+            "" => DEFAULT_ENCRYPTION_KEY_ID,
+            key_id => key_id,
+        };
+        validate_encryption_key_id(key_id)?;
+
+        Ok((key_id, self.encryption_key(Some(key_id))?))
+    }
+
+    /// The key a ciphertext names, `None` stands for ciphertexts written before keys had ids.
+    pub fn encryption_key(&self, key_id: Option<&str>) -> Result<&AesGcmCipher, String> {
+        match key_id {
+            None => self
+                .unversioned_cipher
+                .as_ref()
+                .ok_or_else(|| "`secrets.aes256_gcm_key` is empty".to_string()),
+            Some(key_id) => self
+                .ciphers
+                .get(key_id)
+                .ok_or_else(|| format!("Unknown encryption key id: {}", key_id)),
+        }
+    }
+}
+
+/// The current [`Secrets`], replaced each time they are resolved again.
+///
+/// Readers hold on to the `Arc` they got, a refresh never changes secrets mid-request.
+#[derive(Clone, Debug, Default)]
+pub struct SecretStore(Arc<RwLock<Arc<Secrets>>>);
+
+impl SecretStore {
+    pub fn new(secrets: Secrets) -> Self {
+        SecretStore(Arc::new(RwLock::new(Arc::new(secrets))))
+    }
+
+    pub fn current(&self) -> Arc<Secrets> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, secrets: Secrets) {
+        *self.0.write().unwrap() = Arc::new(secrets);
+    }
+}
+
+/// Key ids are written into each ciphertext, see [`crate::utils::VersionedCiphertext`].
+pub fn validate_encryption_key_id(key_id: &str) -> Result<(), String> {
+    if key_id.is_empty()
+        || key_id.len() > 32
+        || !key_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid encryption key id {:?}, expected up to 32 letters, digits, '-' or '_'",
+            key_id
+        ));
+    }
+
+    Ok(())
 }
 
 pub enum Environment {
@@ -0,0 +1,70 @@
+use serde::de::value::StrDeserializer;
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ListOrString<T> {
+    List(Vec<T>),
+    String(String),
+}
+
+/// Accepts a list or a comma separated string, environment variables can only hold the latter.
+pub fn deserialize_list_from_string<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    match ListOrString::<T>::deserialize(deserializer)? {
+        ListOrString::List(list) => Ok(list),
+        ListOrString::String(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                T::deserialize(StrDeserializer::<de::value::Error>::new(item)).map_err(|e| {
+                    de::Error::custom(format!("Failed to parse '{}' as a list item: {}", item, e))
+                })
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct TestStruct {
+        #[serde(deserialize_with = "deserialize_list_from_string")]
+        colors: Vec<Color>,
+    }
+
+    #[test]
+    fn test_list_and_string() {
+        let from_list: TestStruct =
+            serde_json::from_value(json!({ "colors": ["green", "red"] })).unwrap();
+        let from_string: TestStruct =
+            serde_json::from_value(json!({ "colors": "green, red" })).unwrap();
+        let from_empty_string: TestStruct =
+            serde_json::from_value(json!({ "colors": "" })).unwrap();
+
+        assert_eq!(from_list.colors, vec![Color::Green, Color::Red]);
+        assert_eq!(from_string.colors, vec![Color::Green, Color::Red]);
+        assert!(from_empty_string.colors.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_item() {
+        let result = serde_json::from_value::<TestStruct>(json!({ "colors": "green,blue" }));
+
+        assert!(result.is_err());
+    }
+}
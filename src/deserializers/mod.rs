@@ -1,5 +1,7 @@
+mod deserialize_list_from_string;
 mod deserialize_number_from_string;
 mod deserialize_option_number_from_string;
 
+pub use deserialize_list_from_string::*;
 pub use deserialize_number_from_string::*;
 pub use deserialize_option_number_from_string::*;
@@ -45,3 +45,8 @@ pub async fn run_migrations(pool: &PgPool) -> Result<(), MigrateError> {
 
     Ok(())
 }
+
+/// Tenant scoped schema, applied to dedicated tenant databases (see `migrations/tenant`).
+pub async fn run_tenant_migrations(pool: &PgPool) -> Result<(), MigrateError> {
+    sqlx::migrate!("./migrations/tenant").run(pool).await
+}
@@ -0,0 +1,38 @@
+use crate::models::ApiKeyScope;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Keys look like `tk_<prefix>_<secret>`, e.g. in `Authorization: Bearer tk_...`.
+pub const API_KEY_PREFIX: &str = "tk_";
+
+/// A tenant scoped API key, without the hash of its secret.
+#[derive(Serialize, Deserialize, FromRow, Debug, Clone, Default)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    /// Identifies the key, it is part of the key itself.
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    pub const COLUMNS: &'static str =
+        "id, tenant_id, name, prefix, scopes, expires_at, revoked_at, created_at";
+
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.iter().any(|granted| granted == scope.as_str())
+    }
+}
+
+/// The verified key of the current request, see `ApiKeyAuthentication`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedApiKey {
+    pub api_key: ApiKey,
+    pub tenant_name: String,
+}
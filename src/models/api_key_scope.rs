@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// What an API key may be used for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    #[serde(rename = "users:read")]
+    UsersRead,
+    #[serde(rename = "users:write")]
+    UsersWrite,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::UsersRead => "users:read",
+            ApiKeyScope::UsersWrite => "users:write",
+        }
+    }
+}
+
+impl TryFrom<String> for ApiKeyScope {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "users:read" => Ok(Self::UsersRead),
+            "users:write" => Ok(Self::UsersWrite),
+            other => Err(format!("{} is not a supported API key scope.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_round_trip() {
+        for scope in [ApiKeyScope::UsersRead, ApiKeyScope::UsersWrite] {
+            assert_eq!(ApiKeyScope::try_from(scope.as_str().to_string()), Ok(scope));
+            assert_eq!(
+                serde_json::to_value(scope).unwrap(),
+                serde_json::json!(scope.as_str())
+            );
+        }
+
+        assert!(ApiKeyScope::try_from("users:delete".to_string()).is_err());
+    }
+}
@@ -1,9 +1,7 @@
-use crate::models::TenantPool;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use uuid::Uuid;
+use crate::models::{SharedTenantPool, TenantPoolRegistry};
 
 pub struct AppState {
-    pub pools: Arc<Mutex<HashMap<Uuid, Arc<Mutex<TenantPool>>>>>,
+    pub pools: TenantPoolRegistry,
+    /// Set when `tenant_pools.strategy` is one of the shared strategies.
+    pub shared_pool: Option<SharedTenantPool>,
 }
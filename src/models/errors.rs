@@ -1,4 +1,5 @@
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
 use anyhow::Error as AnyhowError;
 use config::ConfigError;
 use sqlx::error::Error as SqlxError;
@@ -37,6 +38,21 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequestError(String),
 
+    #[error("Conflict: {0}")]
+    ConflictError(String),
+
+    #[error("Tenant is suspended")]
+    TenantSuspended,
+
+    #[error("Tenant has been deleted")]
+    TenantDeleted,
+
+    #[error("Ciphertext is not bound to tenant {tenant_id}")]
+    CiphertextBindingMismatch { tenant_id: uuid::Uuid },
+
+    #[error("Tenant pool capacity exhausted")]
+    TenantPoolCapacityExhausted { retry_after_in_seconds: u64 },
+
     #[error("Not content: {0}")]
     NoContentError(String),
 
@@ -50,11 +66,17 @@ pub enum AppError {
 impl ResponseError for AppError {
     fn status_code(&self) -> StatusCode {
         match *self {
-            AppError::DatabaseError(_) | AppError::InternalError(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            AppError::DatabaseError(_)
+            | AppError::InternalError(_)
+            | AppError::CiphertextBindingMismatch { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::NotFoundError => StatusCode::NOT_FOUND,
+            AppError::AuthenticationFailed(_) => StatusCode::UNAUTHORIZED,
+            AppError::AuthorizationFailed(_) => StatusCode::FORBIDDEN,
             AppError::BadRequestError(_) => StatusCode::BAD_REQUEST,
+            AppError::ConflictError(_) => StatusCode::CONFLICT,
+            AppError::TenantSuspended => StatusCode::FORBIDDEN,
+            AppError::TenantDeleted => StatusCode::GONE,
+            AppError::TenantPoolCapacityExhausted { .. } => StatusCode::SERVICE_UNAVAILABLE,
             AppError::NoContentError(_) => StatusCode::NO_CONTENT,
             // Handle other error types
             _ => StatusCode::NOT_FOUND,
@@ -66,12 +88,26 @@ impl ResponseError for AppError {
             AppError::DatabaseError(_) => {
                 HttpResponse::InternalServerError().body("Database error.")
             }
-            AppError::InternalError(_) | AppError::OpenSslError(_) => {
+            AppError::InternalError(_)
+            | AppError::OpenSslError(_)
+            | AppError::CiphertextBindingMismatch { .. } => {
                 HttpResponse::InternalServerError().body("Something went terribly wrong.")
             }
             AppError::NotFoundError => HttpResponse::NotFound().body("Resource not found."),
             AppError::NoContentError(_) => HttpResponse::NoContent().body("No content available."),
+            AppError::AuthenticationFailed(_) => {
+                HttpResponse::Unauthorized().body("Authentication failed.")
+            }
+            AppError::AuthorizationFailed(_) => HttpResponse::Forbidden().body("Access denied."),
             AppError::BadRequestError(_) => HttpResponse::BadRequest().body("Bad request"),
+            AppError::ConflictError(_) => HttpResponse::Conflict().body("Conflict"),
+            AppError::TenantSuspended => HttpResponse::Forbidden().body("Tenant is suspended."),
+            AppError::TenantDeleted => HttpResponse::Gone().body("Tenant has been deleted."),
+            AppError::TenantPoolCapacityExhausted {
+                retry_after_in_seconds,
+            } => HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, *retry_after_in_seconds))
+                .body("Tenant pool capacity exhausted, please retry later."),
             _ => HttpResponse::InternalServerError().body("Something went terribly wrong."),
         }
     }
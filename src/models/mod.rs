@@ -1,13 +1,35 @@
+mod api_key;
+mod api_key_scope;
 mod app_state;
 mod errors;
+mod shared_tenant_pool;
 mod tenant;
 mod tenant_credentials;
+mod tenant_database;
+mod tenant_deletion_record;
 mod tenant_pool;
+mod tenant_pool_metrics;
+mod tenant_pool_registry;
+mod tenant_pool_settings;
+mod tenant_status;
+mod tenant_transaction_settings;
 mod user;
+mod user_credentials;
 
+pub use api_key::*;
+pub use api_key_scope::*;
 pub use app_state::*;
 pub use errors::*;
+pub use shared_tenant_pool::*;
 pub use tenant::*;
 pub use tenant_credentials::*;
+pub use tenant_database::*;
+pub use tenant_deletion_record::*;
 pub use tenant_pool::*;
+pub use tenant_pool_metrics::*;
+pub use tenant_pool_registry::*;
+pub use tenant_pool_settings::*;
+pub use tenant_status::*;
+pub use tenant_transaction_settings::*;
 pub use user::*;
+pub use user_credentials::*;
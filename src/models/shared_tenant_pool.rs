@@ -0,0 +1,114 @@
+use crate::configurations::TenantPoolStrategy;
+use crate::models::{TenantIsolationMode, TenantStatus, TenantTransactionSettings};
+use crate::utils::quote_identifier;
+use dashmap::DashMap;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Role the switcher assumes under [`TenantPoolStrategy::SharedTenantSetting`].
+pub const TENANT_SHARED_ROLE: &str = "tenant_shared";
+
+/// What the shared pool needs to know about a tenant to switch to it.
+#[derive(Clone, Debug)]
+pub struct SharedPoolTenant {
+    pub db_user: String,
+    pub isolation_mode: TenantIsolationMode,
+    pub status: TenantStatus,
+}
+
+/// A single pool, logged in as the switcher role, serving every tenant of the shared database.
+///
+/// Connections carry no tenant on their own: [`SharedTenantPool::begin`] switches the
+/// transaction to the tenant with `SET LOCAL`, which Postgres undoes on commit or rollback.
+pub struct SharedTenantPool {
+    pub pool: PgPool,
+    strategy: TenantPoolStrategy,
+    tenants: DashMap<Uuid, SharedPoolTenant>,
+}
+
+impl SharedTenantPool {
+    pub fn new(pool: PgPool, strategy: TenantPoolStrategy) -> Self {
+        SharedTenantPool {
+            pool,
+            strategy,
+            tenants: DashMap::new(),
+        }
+    }
+
+    pub fn strategy(&self) -> TenantPoolStrategy {
+        self.strategy
+    }
+
+    pub fn tenant(&self, tenant_id: &Uuid) -> Option<SharedPoolTenant> {
+        self.tenants.get(tenant_id).map(|tenant| tenant.clone())
+    }
+
+    pub fn remember(&self, tenant_id: Uuid, tenant: SharedPoolTenant) {
+        self.tenants.insert(tenant_id, tenant);
+    }
+
+    /// Drops what is known about the tenant, e.g. after its isolation mode or status changed.
+    pub fn forget(&self, tenant_id: &Uuid) {
+        self.tenants.remove(tenant_id);
+    }
+
+    /// Starts a transaction acting as the given tenant.
+    pub async fn begin(
+        &self,
+        tenant_id: &Uuid,
+        tenant: &SharedPoolTenant,
+        settings: &TenantTransactionSettings,
+    ) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        let mut transaction = self.pool.begin_with(settings.begin_statement()).await?;
+
+        if let Some(statement) = set_role_statement(self.strategy, tenant) {
+            sqlx::query(&statement).execute(&mut *transaction).await?;
+        }
+
+        if self.strategy == TenantPoolStrategy::SharedTenantSetting {
+            sqlx::query("SELECT set_config('app.current_tenant', $1, true)")
+                .bind(tenant_id.to_string())
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        Ok(transaction)
+    }
+}
+
+fn set_role_statement(strategy: TenantPoolStrategy, tenant: &SharedPoolTenant) -> Option<String> {
+    let role = match strategy {
+        TenantPoolStrategy::PerTenant => return None,
+        TenantPoolStrategy::SharedSetRole => tenant.db_user.as_str(),
+        TenantPoolStrategy::SharedTenantSetting => TENANT_SHARED_ROLE,
+    };
+
+    // Utility statements can't be parameterised, identifiers are quoted instead
+    Some(format!("SET LOCAL ROLE {}", quote_identifier(role)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant() -> SharedPoolTenant {
+        SharedPoolTenant {
+            db_user: "tenant_base_alex".to_string(),
+            isolation_mode: TenantIsolationMode::Shared,
+            status: TenantStatus::Active,
+        }
+    }
+
+    #[test]
+    fn test_set_role_statement() {
+        assert_eq!(
+            set_role_statement(TenantPoolStrategy::SharedSetRole, &tenant()).as_deref(),
+            Some("SET LOCAL ROLE \"tenant_base_alex\"")
+        );
+        assert_eq!(
+            set_role_statement(TenantPoolStrategy::SharedTenantSetting, &tenant()).as_deref(),
+            Some("SET LOCAL ROLE \"tenant_shared\"")
+        );
+        assert!(set_role_statement(TenantPoolStrategy::PerTenant, &tenant()).is_none());
+    }
+}
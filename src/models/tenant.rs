@@ -1,8 +1,12 @@
+use crate::models::{TenantDatabaseSettings, TenantPoolSettings};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+/// Group role every tenant role is a member of, see `setup_tenant_base_role(..)`.
+pub const TENANT_BASE_ROLE: &str = "tenant_base";
+
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct Tenant {
     pub id: Option<Uuid>,
@@ -12,6 +16,73 @@ pub struct Tenant {
     pub db_user: String,
     #[serde(skip)]
     pub db_password_encrypted: Option<String>,
+    pub pool_min_connections: Option<i32>,
+    pub pool_max_connections: Option<i32>,
+    pub pool_acquire_timeout: Option<i64>,
+    pub pool_max_lifetime: Option<i64>,
+    pub pool_idle_timeout: Option<i64>,
+    pub warm: bool,
+    pub isolation_mode: String,
+    pub db_host: Option<String>,
+    pub db_port: Option<i32>,
+    pub db_name: Option<String>,
+    pub db_ssl_mode: Option<String>,
+    pub db_schema: Option<String>,
+    pub status: String,
+    pub status_changed_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
+
+impl Tenant {
+    pub fn pool_settings(&self) -> TenantPoolSettings {
+        TenantPoolSettings {
+            min_connections: self.pool_min_connections,
+            max_connections: self.pool_max_connections,
+            acquire_timeout: self.pool_acquire_timeout,
+            max_lifetime: self.pool_max_lifetime,
+            idle_timeout: self.pool_idle_timeout,
+        }
+    }
+
+    pub fn database_settings(&self) -> TenantDatabaseSettings {
+        TenantDatabaseSettings {
+            host: self.db_host.clone(),
+            port: self.db_port.map(|port| port as u16),
+            database_name: self.db_name.clone(),
+            ssl_mode: self.db_ssl_mode.clone(),
+        }
+    }
+}
+
+/// Public view of a tenant, the database credentials are never selected.
+#[derive(Serialize, Deserialize, FromRow, Default)]
+pub struct TenantSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub isolation_mode: String,
+    pub warm: bool,
+    pub status: String,
+    pub status_changed_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl TenantSummary {
+    pub const COLUMNS: &'static str =
+        "id, name, isolation_mode, warm, status, status_changed_at, created_at, updated_at";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_summary_never_exposes_credentials() {
+        let summary = serde_json::to_value(TenantSummary::default()).unwrap();
+
+        assert!(summary.get("db_user").is_none());
+        assert!(summary.get("db_password_encrypted").is_none());
+        assert!(!TenantSummary::COLUMNS.contains("db_"));
+    }
+}
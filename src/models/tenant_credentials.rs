@@ -1,10 +1,19 @@
+use crate::models::{
+    TenantDatabaseSettings, TenantIsolationMode, TenantPoolSettings, TenantStatus,
+};
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
 
-#[derive(Serialize, Deserialize, FromRow)]
+#[derive(Serialize, Deserialize)]
 pub struct TenantCredentials {
     pub db_user: String,
     #[serde(skip)]
     pub db_password: SecretString,
+    pub pool_settings: TenantPoolSettings,
+    pub warm: bool,
+    pub isolation_mode: TenantIsolationMode,
+    pub database: TenantDatabaseSettings,
+    /// Set for tenants isolated by schema, their connections use it as `search_path`.
+    pub schema: Option<String>,
+    pub status: TenantStatus,
 }
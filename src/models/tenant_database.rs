@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgSslMode;
+use std::str::FromStr;
+
+/// How a tenant's data is isolated from the other tenants.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantIsolationMode {
+    /// Rows live in the application database, isolated by row level security.
+    #[default]
+    Shared,
+    /// The tenant's tables live in a schema of its own, within the application database.
+    Schema,
+    /// The tenant has a database of its own.
+    Database,
+}
+
+impl TenantIsolationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TenantIsolationMode::Shared => "shared",
+            TenantIsolationMode::Schema => "schema",
+            TenantIsolationMode::Database => "database",
+        }
+    }
+}
+
+impl TryFrom<String> for TenantIsolationMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "shared" => Ok(Self::Shared),
+            "schema" => Ok(Self::Schema),
+            "database" => Ok(Self::Database),
+            other => Err(format!(
+                "{} is not a supported tenant isolation mode.",
+                other
+            )),
+        }
+    }
+}
+
+/// Where a tenant's database lives. Unset values fall back to the application database.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct TenantDatabaseSettings {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub database_name: Option<String>,
+    pub ssl_mode: Option<String>,
+}
+
+impl TenantDatabaseSettings {
+    pub fn parsed_ssl_mode(&self) -> Result<Option<PgSslMode>, String> {
+        self.ssl_mode
+            .as_deref()
+            .map(|ssl_mode| {
+                PgSslMode::from_str(ssl_mode)
+                    .map_err(|_| format!("{} is not a supported SSL mode.", ssl_mode))
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolation_mode_round_trip() {
+        for mode in [
+            TenantIsolationMode::Shared,
+            TenantIsolationMode::Schema,
+            TenantIsolationMode::Database,
+        ] {
+            assert_eq!(
+                TenantIsolationMode::try_from(mode.as_str().to_string()),
+                Ok(mode)
+            );
+        }
+        assert!(TenantIsolationMode::try_from("cluster".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parsed_ssl_mode() {
+        let settings = TenantDatabaseSettings {
+            ssl_mode: Some("verify-full".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            settings.parsed_ssl_mode(),
+            Ok(Some(PgSslMode::VerifyFull))
+        ));
+
+        let settings = TenantDatabaseSettings {
+            ssl_mode: Some("sometimes".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.parsed_ssl_mode().is_err());
+
+        assert!(matches!(
+            TenantDatabaseSettings::default().parsed_ssl_mode(),
+            Ok(None)
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Audit record of an offboarded tenant, see `delete_tenant_with_role(..)`.
+#[derive(Serialize, Deserialize, FromRow, Debug)]
+pub struct TenantDeletionRecord {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub tenant_name: String,
+    pub db_user: String,
+    pub terminated_backends: i32,
+    pub deleted_at: DateTime<Utc>,
+}
@@ -1,8 +1,80 @@
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 pub struct TenantPool {
     pub pool: Arc<PgPool>,
-    pub last_accessed: DateTime<Utc>,
+    // Stored as epoch milliseconds so that touching the pool never needs a lock.
+    last_accessed: AtomicI64,
+    draining: AtomicBool,
+    keep_warm: bool,
+}
+
+impl TenantPool {
+    pub fn new(pool: PgPool) -> Self {
+        Self::with_last_accessed(pool, Utc::now())
+    }
+
+    pub fn with_last_accessed(pool: PgPool, last_accessed: DateTime<Utc>) -> Self {
+        TenantPool {
+            pool: Arc::new(pool),
+            last_accessed: AtomicI64::new(last_accessed.timestamp_millis()),
+            draining: AtomicBool::new(false),
+            keep_warm: false,
+        }
+    }
+
+    /// Exempts the pool from idle and capacity eviction.
+    pub fn with_keep_warm(mut self, keep_warm: bool) -> Self {
+        self.keep_warm = keep_warm;
+        self
+    }
+
+    pub fn is_kept_warm(&self) -> bool {
+        self.keep_warm
+    }
+
+    /// Mark the pool as visited right now.
+    pub fn touch(&self) {
+        self.last_accessed
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// The number of connections this pool may open, used for the global connection budget.
+    pub fn max_connections(&self) -> u32 {
+        self.pool.options().get_max_connections()
+    }
+
+    pub fn last_accessed(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.last_accessed.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    pub fn mark_draining(&self) {
+        self.draining.store(true, Ordering::Release);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// Waits until nobody outside this `TenantPool` holds the `Arc<PgPool>` anymore.
+    /// Returns `false` if holders were still around when `timeout` elapsed.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        while Arc::strong_count(&self.pool) > 1 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+
+        true
+    }
 }
@@ -0,0 +1,115 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Why a tenant pool was taken out of the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    Idle,
+    Capacity,
+    Reconfigured,
+    /// The pool could no longer connect or authenticate.
+    Broken,
+    /// The tenant's database password was rotated.
+    Rotated,
+    /// The tenant was suspended or deleted, its pool is closed without draining.
+    Deactivated,
+}
+
+impl EvictionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionReason::Idle => "idle",
+            EvictionReason::Capacity => "capacity",
+            EvictionReason::Reconfigured => "reconfigured",
+            EvictionReason::Broken => "broken",
+            EvictionReason::Rotated => "rotated",
+            EvictionReason::Deactivated => "deactivated",
+        }
+    }
+
+    /// Whether the evicted pool keeps serving checkouts until it is idle or the drain timeout.
+    pub fn drains(&self) -> bool {
+        !matches!(self, EvictionReason::Deactivated)
+    }
+}
+
+/// Process-wide counters for tenant pool evictions and rebuilds.
+#[derive(Default)]
+pub struct TenantPoolMetrics {
+    evicted_idle: AtomicU64,
+    evicted_capacity: AtomicU64,
+    evicted_reconfigured: AtomicU64,
+    evicted_broken: AtomicU64,
+    evicted_rotated: AtomicU64,
+    evicted_deactivated: AtomicU64,
+    rebuilt: AtomicU64,
+    rebuild_failed: AtomicU64,
+    drained: AtomicU64,
+    drain_timed_out: AtomicU64,
+    closed: AtomicU64,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TenantPoolMetricsSnapshot {
+    pub evicted_idle: u64,
+    pub evicted_capacity: u64,
+    pub evicted_reconfigured: u64,
+    pub evicted_broken: u64,
+    pub evicted_rotated: u64,
+    pub evicted_deactivated: u64,
+    pub rebuilt: u64,
+    pub rebuild_failed: u64,
+    pub drained: u64,
+    pub drain_timed_out: u64,
+    pub closed: u64,
+}
+
+impl TenantPoolMetrics {
+    pub fn record_eviction(&self, reason: EvictionReason) {
+        let counter = match reason {
+            EvictionReason::Idle => &self.evicted_idle,
+            EvictionReason::Capacity => &self.evicted_capacity,
+            EvictionReason::Reconfigured => &self.evicted_reconfigured,
+            EvictionReason::Broken => &self.evicted_broken,
+            EvictionReason::Rotated => &self.evicted_rotated,
+            EvictionReason::Deactivated => &self.evicted_deactivated,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rebuilt(&self) {
+        self.rebuilt.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rebuild_failed(&self) {
+        self.rebuild_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_drained(&self) {
+        self.drained.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_drain_timed_out(&self) {
+        self.drain_timed_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_closed(&self) {
+        self.closed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TenantPoolMetricsSnapshot {
+        TenantPoolMetricsSnapshot {
+            evicted_idle: self.evicted_idle.load(Ordering::Relaxed),
+            evicted_capacity: self.evicted_capacity.load(Ordering::Relaxed),
+            evicted_reconfigured: self.evicted_reconfigured.load(Ordering::Relaxed),
+            evicted_broken: self.evicted_broken.load(Ordering::Relaxed),
+            evicted_rotated: self.evicted_rotated.load(Ordering::Relaxed),
+            evicted_deactivated: self.evicted_deactivated.load(Ordering::Relaxed),
+            rebuilt: self.rebuilt.load(Ordering::Relaxed),
+            rebuild_failed: self.rebuild_failed.load(Ordering::Relaxed),
+            drained: self.drained.load(Ordering::Relaxed),
+            drain_timed_out: self.drain_timed_out.load(Ordering::Relaxed),
+            closed: self.closed.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{Validate, ValidationError};
+
+/// Per-tenant connection pool overrides stored on the `tenants` row.
+/// Unset values fall back to the global `DatabaseConfiguration`.
+#[derive(Serialize, Deserialize, FromRow, Default, Validate, Clone, Debug, PartialEq, Eq)]
+#[validate(schema(function = "validate_connections_range"))]
+pub struct TenantPoolSettings {
+    #[validate(range(min = 0, max = 1000))]
+    pub min_connections: Option<i32>,
+
+    #[validate(range(min = 1, max = 1000))]
+    pub max_connections: Option<i32>,
+
+    // Milliseconds
+    #[validate(range(min = 1))]
+    pub acquire_timeout: Option<i64>,
+
+    // Seconds
+    #[validate(range(min = 1))]
+    pub max_lifetime: Option<i64>,
+
+    // Seconds
+    #[validate(range(min = 1))]
+    pub idle_timeout: Option<i64>,
+}
+
+fn validate_connections_range(settings: &TenantPoolSettings) -> Result<(), ValidationError> {
+    if let (Some(min_connections), Some(max_connections)) =
+        (settings.min_connections, settings.max_connections)
+    {
+        if min_connections > max_connections {
+            return Err(ValidationError::new(
+                "min_connections must not exceed max_connections",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_settings_are_valid() {
+        assert!(TenantPoolSettings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_min_connections_above_max_connections_is_invalid() {
+        let settings = TenantPoolSettings {
+            min_connections: Some(10),
+            max_connections: Some(2),
+            ..Default::default()
+        };
+
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_invalid() {
+        let settings = TenantPoolSettings {
+            max_connections: Some(0),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+
+        let settings = TenantPoolSettings {
+            acquire_timeout: Some(-5),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::models::AppError;
+use serde::{Deserialize, Serialize};
+
+/// Where a tenant is in its lifecycle. Only active tenants are served.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TenantStatus {
+    #[default]
+    Active,
+    /// Blocked, its role and data are kept.
+    Suspended,
+    /// Blocked and hard deleted once the grace period ends, unless it is restored before.
+    PendingDeletion,
+    /// Being hard deleted, there is no way back.
+    Deleted,
+}
+
+impl TenantStatus {
+    pub const ALL: [TenantStatus; 4] = [
+        TenantStatus::Active,
+        TenantStatus::Suspended,
+        TenantStatus::PendingDeletion,
+        TenantStatus::Deleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TenantStatus::Active => "active",
+            TenantStatus::Suspended => "suspended",
+            TenantStatus::PendingDeletion => "pending_deletion",
+            TenantStatus::Deleted => "deleted",
+        }
+    }
+
+    /// Status changes allowed through the API, `deleted` is only ever set by the deletion job.
+    pub fn can_transition_to(&self, next: TenantStatus) -> bool {
+        matches!(
+            (self, next),
+            (TenantStatus::Active, TenantStatus::Suspended)
+                | (TenantStatus::Active, TenantStatus::PendingDeletion)
+                | (TenantStatus::Suspended, TenantStatus::Active)
+                | (TenantStatus::Suspended, TenantStatus::PendingDeletion)
+                | (TenantStatus::PendingDeletion, TenantStatus::Active)
+                | (TenantStatus::PendingDeletion, TenantStatus::Suspended)
+        )
+    }
+
+    /// Fails with `403` for suspended tenants and `410` for deleted ones.
+    pub fn ensure_active(&self) -> Result<(), AppError> {
+        match self {
+            TenantStatus::Active => Ok(()),
+            TenantStatus::Suspended => Err(AppError::TenantSuspended),
+            TenantStatus::PendingDeletion | TenantStatus::Deleted => Err(AppError::TenantDeleted),
+        }
+    }
+}
+
+impl TryFrom<String> for TenantStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "active" => Ok(Self::Active),
+            "suspended" => Ok(Self::Suspended),
+            "pending_deletion" => Ok(Self::PendingDeletion),
+            "deleted" => Ok(Self::Deleted),
+            other => Err(format!("{} is not a supported tenant status.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+
+    #[test]
+    fn test_transitions() {
+        assert!(TenantStatus::Active.can_transition_to(TenantStatus::Suspended));
+        assert!(TenantStatus::Suspended.can_transition_to(TenantStatus::Active));
+        assert!(TenantStatus::Active.can_transition_to(TenantStatus::PendingDeletion));
+        assert!(TenantStatus::PendingDeletion.can_transition_to(TenantStatus::Active));
+
+        assert!(!TenantStatus::Active.can_transition_to(TenantStatus::Active));
+        assert!(!TenantStatus::Active.can_transition_to(TenantStatus::Deleted));
+        assert!(!TenantStatus::PendingDeletion.can_transition_to(TenantStatus::Deleted));
+        assert!(!TenantStatus::Deleted.can_transition_to(TenantStatus::Active));
+    }
+
+    #[test]
+    fn test_only_active_tenants_are_served() {
+        let status_code = |status: TenantStatus| status.ensure_active().unwrap_err().status_code();
+
+        assert!(TenantStatus::Active.ensure_active().is_ok());
+        assert_eq!(status_code(TenantStatus::Suspended), StatusCode::FORBIDDEN);
+        assert_eq!(status_code(TenantStatus::PendingDeletion), StatusCode::GONE);
+        assert_eq!(status_code(TenantStatus::Deleted), StatusCode::GONE);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for status in TenantStatus::ALL {
+            assert_eq!(
+                TenantStatus::try_from(status.as_str().to_string()),
+                Ok(status)
+            );
+        }
+
+        assert!(TenantStatus::try_from("archived".to_string()).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IsolationLevel {
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+/// How a tenant transaction is started, unset values keep the server defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TenantTransactionSettings {
+    pub isolation_level: Option<IsolationLevel>,
+    /// Applied with `SET LOCAL`, so it only lasts for the transaction.
+    pub statement_timeout: Option<Duration>,
+}
+
+impl TenantTransactionSettings {
+    pub fn with_isolation_level(mut self, isolation_level: IsolationLevel) -> Self {
+        self.isolation_level = Some(isolation_level);
+        self
+    }
+
+    pub fn with_statement_timeout(mut self, statement_timeout: Duration) -> Self {
+        self.statement_timeout = Some(statement_timeout);
+        self
+    }
+
+    /// The isolation level is part of `BEGIN`, it can't be changed once the transaction
+    /// ran a query, e.g. `set_config(..)` of the shared pool strategies.
+    pub fn begin_statement(&self) -> Cow<'static, str> {
+        match self.isolation_level {
+            Some(isolation_level) => Cow::Owned(format!(
+                "BEGIN ISOLATION LEVEL {}",
+                isolation_level.as_str()
+            )),
+            None => Cow::Borrowed("BEGIN"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_begin_statement() {
+        assert_eq!(
+            TenantTransactionSettings::default().begin_statement(),
+            "BEGIN"
+        );
+        assert_eq!(
+            TenantTransactionSettings::default()
+                .with_isolation_level(IsolationLevel::Serializable)
+                .begin_statement(),
+            "BEGIN ISOLATION LEVEL SERIALIZABLE"
+        );
+    }
+}
@@ -30,6 +30,10 @@ pub struct User {
     ))]
     pub last_name: String,
 
+    // `email` is the login of users registered with a password, unique within a tenant.
+    #[validate(email(message = "Email must be a valid email address"))]
+    pub email: Option<String>,
+
     // `confirmed` is a boolean field, so no validation is needed as it’s either `true` or `false`.
     #[serde(skip_deserializing)]
     pub confirmed: Option<bool>,
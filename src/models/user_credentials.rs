@@ -0,0 +1,81 @@
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+pub const MIN_PASSWORD_LENGTH: usize = 12;
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+#[derive(Deserialize, Debug, Validate)]
+#[validate(schema(function = "validate_password"))]
+pub struct UserRegistration {
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "First name must be between 1 and 50 characters"
+    ))]
+    pub first_name: String,
+
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Last name must be between 1 and 50 characters"
+    ))]
+    pub last_name: String,
+
+    #[validate(email(message = "Email must be a valid email address"))]
+    pub email: String,
+
+    pub password: SecretString,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserCredentials {
+    pub email: String,
+    pub password: SecretString,
+}
+
+// Bounded above as well, hashing is the expensive part of a request.
+fn validate_password(registration: &UserRegistration) -> Result<(), ValidationError> {
+    let length = registration.password.expose_secret().chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length) {
+        return Err(ValidationError::new("password_length").with_message(
+            format!(
+                "Password must be between {} and {} characters",
+                MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH
+            )
+            .into(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registration(email: &str, password: &str) -> UserRegistration {
+        UserRegistration {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            email: email.to_string(),
+            password: SecretString::from(password),
+        }
+    }
+
+    #[test]
+    fn test_validate_user_registration() {
+        assert!(registration("ada@example.com", "correct horse battery")
+            .validate()
+            .is_ok());
+        assert!(registration("not an email", "correct horse battery")
+            .validate()
+            .is_err());
+        assert!(registration("ada@example.com", "short").validate().is_err());
+        assert!(
+            registration("ada@example.com", &"x".repeat(MAX_PASSWORD_LENGTH + 1))
+                .validate()
+                .is_err()
+        );
+    }
+}
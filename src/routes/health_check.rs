@@ -1,5 +1,6 @@
 use actix_web::HttpResponse;
 
+/// Liveness only, the tenant pools are reported by `GET /internal/tenant-pools`.
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
 }
@@ -0,0 +1,71 @@
+use crate::configurations::Configuration;
+use crate::models::{ApiKey, AppError};
+use crate::utils;
+use crate::utils::NewApiKey;
+use actix_web::{web, HttpResponse};
+use secrecy::ExposeSecret;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::post().to(create_api_key))
+        .route("", web::get().to(get_api_keys))
+        .route("/{api_key_id}", web::delete().to(revoke_api_key));
+}
+
+/// Creates an API key for the tenant, see [`utils::create_api_key`].
+/// The key is only ever part of this response.
+pub async fn create_api_key(
+    path: web::Path<Uuid>,
+    new_api_key: web::Json<NewApiKey>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+
+    let (api_key, key) =
+        utils::create_api_key(&tenant_id, &new_api_key, &pool, &configuration).await?;
+
+    let mut body = json!(api_key);
+    body["key"] = json!(key.expose_secret());
+
+    Ok(HttpResponse::Created().json(body))
+}
+
+pub async fn get_api_keys(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM tenants WHERE id = $1)")
+        .bind(tenant_id)
+        .fetch_one(pool.get_ref())
+        .await?;
+    if !exists {
+        return Err(AppError::NotFoundError);
+    }
+
+    let api_keys = sqlx::query_as::<_, ApiKey>(&format!(
+        "SELECT {} FROM api_keys WHERE tenant_id = $1 ORDER BY created_at",
+        ApiKey::COLUMNS
+    ))
+    .bind(tenant_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!(api_keys)))
+}
+
+/// Revokes the key, requests using it are rejected from now on.
+pub async fn revoke_api_key(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (tenant_id, api_key_id) = path.into_inner();
+
+    let api_key = utils::revoke_api_key(&tenant_id, &api_key_id, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(json!(api_key)))
+}
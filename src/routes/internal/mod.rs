@@ -1,6 +1,13 @@
 use actix_web::web;
+mod api_keys;
+mod tenant_pools;
+mod tenants;
 mod users;
 
 pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/users").configure(users::configure));
+    cfg.service(web::scope("/users").configure(users::configure))
+        // Registered ahead of `/tenants`, which would otherwise match these paths first
+        .service(web::scope("/tenants/{tenant_id}/api-keys").configure(api_keys::configure))
+        .service(web::scope("/tenants").configure(tenants::configure))
+        .service(web::scope("/tenant-pools").configure(tenant_pools::configure));
 }
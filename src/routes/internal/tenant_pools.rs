@@ -0,0 +1,30 @@
+use crate::models::AppState;
+use crate::utils::TenantPoolJanitorHandle;
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(get_tenant_pools))
+        .route("/sweep", web::post().to(sweep_tenant_pools));
+}
+
+/// The janitor's last run and the pool metrics, kept off the public `/health_check`.
+pub async fn get_tenant_pools(
+    state: web::Data<AppState>,
+    tenant_pool_janitor: web::Data<TenantPoolJanitorHandle>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "tenant_pool_janitor": tenant_pool_janitor.status(),
+        "tenant_pools": state.pools.metrics(),
+    }))
+}
+
+pub async fn sweep_tenant_pools(
+    tenant_pool_janitor: web::Data<TenantPoolJanitorHandle>,
+) -> HttpResponse {
+    tenant_pool_janitor.sweep_now();
+
+    HttpResponse::Accepted().json(json!({
+        "last_run_at": tenant_pool_janitor.last_run_at(),
+    }))
+}
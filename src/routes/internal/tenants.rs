@@ -0,0 +1,273 @@
+use crate::configurations::Configuration;
+use crate::models::{
+    AppError, AppState, EvictionReason, TenantDatabaseSettings, TenantIsolationMode,
+    TenantPoolSettings, TenantStatus, TenantSummary,
+};
+use crate::utils;
+use crate::utils::refresh_pool_for_tenant;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use sqlx_paginated::{paginated_query_as, FlatQueryParams};
+use uuid::Uuid;
+use validator::Validate;
+
+pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::post().to(create_tenant))
+        .route("", web::get().to(get_tenants))
+        .route(
+            "/re-encrypt-credentials",
+            web::post().to(reencrypt_tenant_credentials),
+        )
+        .route("/{id}", web::get().to(get_tenant))
+        .route("/{id}", web::delete().to(delete_tenant))
+        .route(
+            "/{id}/pool-settings",
+            web::get().to(get_tenant_pool_settings),
+        )
+        .route(
+            "/{id}/pool-settings",
+            web::put().to(update_tenant_pool_settings),
+        )
+        .route("/{id}/database", web::put().to(provision_tenant_database))
+        .route("/{id}/schema", web::put().to(provision_tenant_schema))
+        .route("/{id}/status", web::put().to(update_tenant_status))
+        .route(
+            "/{id}/rotate-credentials",
+            web::post().to(rotate_tenant_credentials),
+        );
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateTenantRequest {
+    pub name: String,
+}
+
+/// Creates the tenant and its database role, see [`utils::create_tenant`].
+pub async fn create_tenant(
+    request: web::Json<CreateTenantRequest>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let request = request.into_inner();
+
+    let tenant_id = utils::create_tenant(&request.name, &pool, &configuration).await?;
+
+    Ok(HttpResponse::Created().json(json!({
+        "id": tenant_id,
+    })))
+}
+
+pub async fn get_tenants(
+    pool: web::Data<PgPool>,
+    web::Query(params): web::Query<FlatQueryParams>,
+) -> Result<HttpResponse, AppError> {
+    let sql = format!("SELECT {} FROM tenants", TenantSummary::COLUMNS);
+
+    let tenants = paginated_query_as!(TenantSummary, &sql)
+        .with_params(params)
+        .fetch_paginated(&pool)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(json!(tenants)))
+}
+
+pub async fn get_tenant(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+
+    let tenant = sqlx::query_as::<_, TenantSummary>(&format!(
+        "SELECT {} FROM tenants WHERE id = $1",
+        TenantSummary::COLUMNS
+    ))
+    .bind(tenant_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(AppError::NotFoundError)?;
+
+    Ok(HttpResponse::Ok().json(json!(tenant)))
+}
+
+/// Offboards the tenant right away, whatever its status, see [`utils::delete_tenant`].
+pub async fn delete_tenant(
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+
+    let deletion = utils::delete_tenant(&tenant_id, &TenantStatus::ALL, &state, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(json!(deletion)))
+}
+
+pub async fn get_tenant_pool_settings(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+
+    let settings = sqlx::query_as::<_, TenantPoolSettings>(
+        r#"
+        SELECT pool_min_connections AS min_connections,
+               pool_max_connections AS max_connections,
+               pool_acquire_timeout AS acquire_timeout,
+               pool_max_lifetime AS max_lifetime,
+               pool_idle_timeout AS idle_timeout
+        FROM tenants
+        WHERE id = $1
+        "#,
+    )
+    .bind(tenant_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(AppError::NotFoundError)?;
+
+    Ok(HttpResponse::Ok().json(json!(settings)))
+}
+
+/// Stores the tenant's pool overrides and rebuilds its cached pool so they apply right away.
+pub async fn update_tenant_pool_settings(
+    path: web::Path<Uuid>,
+    settings: web::Json<TenantPoolSettings>,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+    let settings = settings.into_inner();
+
+    settings
+        .validate()
+        .map_err(|e| AppError::BadRequestError(e.to_string()))?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE tenants
+        SET pool_min_connections = $2,
+            pool_max_connections = $3,
+            pool_acquire_timeout = $4,
+            pool_max_lifetime = $5,
+            pool_idle_timeout = $6
+        WHERE id = $1
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(settings.min_connections)
+    .bind(settings.max_connections)
+    .bind(settings.acquire_timeout)
+    .bind(settings.max_lifetime)
+    .bind(settings.idle_timeout)
+    .execute(pool.get_ref())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFoundError);
+    }
+
+    refresh_pool_for_tenant(
+        &tenant_id,
+        &state,
+        &pool,
+        &configuration,
+        EvictionReason::Reconfigured,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!(settings)))
+}
+
+/// Moves the tenant onto a dedicated database, see [`utils::provision_tenant_database`].
+pub async fn provision_tenant_database(
+    path: web::Path<Uuid>,
+    settings: web::Json<TenantDatabaseSettings>,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+    let settings = settings.into_inner();
+
+    utils::provision_tenant_database(&tenant_id, &settings, &state, &pool, &configuration).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "isolation_mode": TenantIsolationMode::Database,
+        "database": settings,
+    })))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TenantSchemaRequest {
+    pub schema: String,
+}
+
+/// Moves the tenant onto a dedicated schema, see [`utils::provision_tenant_schema`].
+pub async fn provision_tenant_schema(
+    path: web::Path<Uuid>,
+    request: web::Json<TenantSchemaRequest>,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+    let request = request.into_inner();
+
+    utils::provision_tenant_schema(&tenant_id, &request.schema, &state, &pool, &configuration)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "isolation_mode": TenantIsolationMode::Schema,
+        "schema": request.schema,
+    })))
+}
+
+/// Replaces the tenant's database password, see [`utils::rotate_tenant_credentials`].
+pub async fn rotate_tenant_credentials(
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+
+    let rotated_at =
+        utils::rotate_tenant_credentials(&tenant_id, &state, &pool, &configuration).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "rotated_at": rotated_at,
+    })))
+}
+
+/// Moves every tenant's stored password onto the active keyring key, see
+/// [`utils::reencrypt_tenant_passwords`].
+pub async fn reencrypt_tenant_credentials(
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let reencryption = utils::reencrypt_tenant_passwords(&pool, &configuration).await?;
+
+    Ok(HttpResponse::Ok().json(json!(reencryption)))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TenantStatusRequest {
+    pub status: TenantStatus,
+}
+
+/// Suspends, reactivates or schedules the deletion of a tenant, see [`utils::update_tenant_status`].
+pub async fn update_tenant_status(
+    path: web::Path<Uuid>,
+    request: web::Json<TenantStatusRequest>,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let tenant_id = path.into_inner();
+    let request = request.into_inner();
+
+    let tenant = utils::update_tenant_status(&tenant_id, request.status, &state, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(json!(tenant)))
+}
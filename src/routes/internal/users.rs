@@ -1,32 +1,56 @@
-use crate::configurations::Configuration;
-use crate::models::{AppState, User};
-use crate::utils::{get_pool_for_tenant, get_tenant_id_from_request};
-use actix_web::{web, HttpRequest, HttpResponse};
+use crate::models::{ApiKeyScope, AppError, IsolationLevel, User};
+use crate::utils::{
+    fetch_paginated_on, ApiKeyAuthentication, SessionBackend, TenantContext, TenantTransaction,
+    TenantTransactionScope,
+};
+use actix_web::{web, HttpResponse};
 use serde_json::json;
-use sqlx::PgPool;
-use sqlx_paginated::{paginated_query_as, FlatQueryParams};
+use sqlx_paginated::FlatQueryParams;
+use std::time::Duration;
+use uuid::Uuid;
 
 pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.route("", web::post().to(create_user))
-        .route("", web::get().to(get_users));
+    cfg.route(
+        "",
+        web::post()
+            .to(create_user)
+            .wrap(TenantTransactionScope::new().with_statement_timeout(Duration::from_secs(5)))
+            .wrap(
+                ApiKeyAuthentication::new()
+                    .required()
+                    .with_scope(ApiKeyScope::UsersWrite),
+            ),
+    )
+    .route(
+        "",
+        // The page and its total count are read from the same snapshot
+        web::get()
+            .to(get_users)
+            .wrap(
+                TenantTransactionScope::new()
+                    .with_isolation_level(IsolationLevel::RepeatableRead)
+                    .with_statement_timeout(Duration::from_secs(10)),
+            )
+            .wrap(
+                ApiKeyAuthentication::new()
+                    .required()
+                    .with_scope(ApiKeyScope::UsersRead),
+            ),
+    )
+    .route(
+        "/{user_id}/sessions",
+        web::delete().to(revoke_user_sessions).wrap(
+            ApiKeyAuthentication::new()
+                .required()
+                .with_scope(ApiKeyScope::UsersWrite),
+        ),
+    );
 }
 
 pub async fn create_user(
-    req: HttpRequest,
+    transaction: TenantTransaction,
     user: web::Json<User>,
-    state: web::Data<AppState>,
-    pool: web::Data<PgPool>,
-    configuration: web::Data<Configuration>,
-) -> HttpResponse {
-    let tenant_id = match get_tenant_id_from_request(&req) {
-        Ok(id) => id,
-        Err(e) => return e,
-    };
-    let pool = match get_pool_for_tenant(&tenant_id, &state, &pool, &configuration).await {
-        Ok(pool) => pool,
-        Err(e) => return e,
-    };
-
+) -> Result<HttpResponse, AppError> {
     let user = sqlx::query_as::<_, User>(
         r#"
         INSERT INTO users (first_name, last_name)
@@ -36,34 +60,37 @@ pub async fn create_user(
     )
     .bind(&user.first_name)
     .bind(&user.last_name)
-    .fetch_one(pool.as_ref())
-    .await
-    .unwrap();
+    .fetch_one(&mut *transaction.lock().await)
+    .await?;
 
-    HttpResponse::Ok().json(json!(user))
+    Ok(HttpResponse::Ok().json(json!(user)))
 }
 
 pub async fn get_users(
-    req: HttpRequest,
-    state: web::Data<AppState>,
-    pool: web::Data<PgPool>,
-    configuration: web::Data<Configuration>,
+    transaction: TenantTransaction,
     web::Query(params): web::Query<FlatQueryParams>,
-) -> HttpResponse {
-    let tenant_id = match get_tenant_id_from_request(&req) {
-        Ok(id) => id,
-        Err(e) => return e,
-    };
-    let tenant_pool = match get_pool_for_tenant(&tenant_id, &state, &pool, &configuration).await {
-        Ok(pool) => pool,
-        Err(e) => return e,
-    };
+) -> Result<HttpResponse, AppError> {
+    let users = fetch_paginated_on::<User>(
+        &mut *transaction.lock().await,
+        "SELECT * FROM users",
+        params,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!(users)))
+}
+
+/// Logs the user out of every session, see [`SessionBackend::revoke_user_sessions`].
+pub async fn revoke_user_sessions(
+    tenant: TenantContext,
+    path: web::Path<Uuid>,
+    session_backend: web::Data<SessionBackend>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = path.into_inner();
 
-    let users = paginated_query_as!(User, "SELECT * FROM users")
-        .with_params(params)
-        .fetch_paginated(&tenant_pool)
-        .await
-        .unwrap();
+    let revoked_sessions = session_backend
+        .revoke_user_sessions(&tenant.id(), &user_id)
+        .await?;
 
-    HttpResponse::Ok().json(json!(users))
+    Ok(HttpResponse::Ok().json(json!({ "revoked_sessions": revoked_sessions })))
 }
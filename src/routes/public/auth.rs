@@ -0,0 +1,111 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, AppState, User, UserCredentials, UserRegistration};
+use crate::utils::{
+    begin_tenant_transaction, register_user, verify_user_credentials, SessionBackend,
+    TenantTransaction, TenantTransactionScope, UserSession,
+};
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+
+// Deliberately keyless: end users register and log in to the tenant resolved from the request
+// before they have a session, the other routes act as the tenant stored in the session
+pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/register",
+        web::post().to(register).wrap(TenantTransactionScope::new()),
+    )
+    .route(
+        "/login",
+        web::post().to(login).wrap(TenantTransactionScope::new()),
+    )
+    .route("/logout", web::post().to(logout))
+    .route("/logout-all", web::post().to(logout_all))
+    .route("/me", web::get().to(me));
+}
+
+/// Registers a user of the request's tenant, see [`register_user`].
+pub async fn register(
+    transaction: TenantTransaction,
+    registration: web::Json<UserRegistration>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let user = register_user(
+        &mut *transaction.lock().await,
+        &registration,
+        &configuration,
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(json!(user)))
+}
+
+/// Logs a user of the request's tenant in, the session then holds their id and the tenant's.
+pub async fn login(
+    transaction: TenantTransaction,
+    credentials: web::Json<UserCredentials>,
+    session: Session,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let user =
+        verify_user_credentials(&mut *transaction.lock().await, &credentials, &configuration)
+            .await?;
+
+    UserSession {
+        user_id: user.id.unwrap_or_default(),
+        tenant_id: transaction.tenant().id(),
+    }
+    .start(&session, &configuration)?;
+
+    Ok(HttpResponse::Ok().json(json!(user)))
+}
+
+pub async fn logout(session: Session) -> HttpResponse {
+    UserSession::end(&session);
+
+    HttpResponse::NoContent().finish()
+}
+
+/// Logs the user out of all their sessions, this one included.
+pub async fn logout_all(
+    user_session: UserSession,
+    session: Session,
+    session_backend: web::Data<SessionBackend>,
+) -> Result<HttpResponse, AppError> {
+    let revoked_sessions = session_backend
+        .revoke_user_sessions(&user_session.tenant_id, &user_session.user_id)
+        .await?;
+    UserSession::end(&session);
+
+    Ok(HttpResponse::Ok().json(json!({ "revoked_sessions": revoked_sessions })))
+}
+
+/// The logged in user, read as the tenant stored in the session rather than the request's.
+pub async fn me(
+    user_session: UserSession,
+    session: Session,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+) -> Result<HttpResponse, AppError> {
+    let mut transaction =
+        begin_tenant_transaction(&user_session.tenant_id, &state, &pool, &configuration).await?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_session.user_id)
+        .fetch_optional(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(json!(user))),
+        None => {
+            // The user has been deleted since logging in
+            UserSession::end(&session);
+            Err(AppError::AuthenticationFailed(
+                "Logged in user no longer exists".to_string(),
+            ))
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use actix_web::web;
+mod auth;
 mod products;
 
 pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/products").configure(products::configure));
+    cfg.service(web::scope("/auth").configure(auth::configure))
+        .service(web::scope("/products").configure(products::configure));
 }
@@ -1,12 +1,20 @@
-use crate::configurations::Configuration;
+use crate::configurations::{
+    Configuration, SessionStoreBackend, TenantPoolStrategy, TenantResolutionStrategy,
+};
 use crate::migrations::run_migrations;
-use crate::models::AppState;
+use crate::models::{AppState, SharedTenantPool, TenantPoolLimits, TenantPoolRegistry};
 use crate::routes::{health_check, internal, public};
-use crate::utils::cleanup_idle_tenant_pools;
-use crate::utils::InternalNetworkGuard;
+use crate::utils::{
+    resolve_secrets, secret_provider_from_configuration, warm_up_tenant_pools, SecretRefresh,
+    TenantCredentialsRotation, TenantCredentialsRotationSettings, TenantDeletion,
+    TenantDeletionSettings, TenantPoolJanitor, TenantPoolJanitorHandle, TenantPoolJanitorSettings,
+};
+use crate::utils::{
+    ApiKeyAuthentication, InternalNetworkGuard, PathTenantResolver, RedisSessions, SessionBackend,
+    TenantResolverChain,
+};
 use actix_cors::Cors;
 use actix_session::config::PersistentSession;
-use actix_session::storage::CookieSessionStore;
 use actix_session::SessionMiddleware;
 use actix_web::cookie::time::Duration;
 use actix_web::cookie::Key;
@@ -16,16 +24,17 @@ use actix_web::{http, web, HttpServer};
 use secrecy::ExposeSecret;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
-use std::collections::HashMap;
 use std::net::TcpListener;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 use tokio::time;
 use tracing_actix_web::TracingLogger;
 
 pub struct Application {
     port: u16,
     server: Server,
+    tenant_pool_janitor: TenantPoolJanitor,
+    tenant_credentials_rotation: Option<TenantCredentialsRotation>,
+    tenant_deletion: TenantDeletion,
+    secret_refresh: Option<SecretRefresh>,
 }
 
 impl Application {
@@ -33,6 +42,33 @@ impl Application {
         configuration: Configuration,
         pool: Option<PgPool>,
     ) -> Result<Self, std::io::Error> {
+        // Everything else reads the secrets resolved here, see `secrets.provider`
+        let secret_provider = secret_provider_from_configuration(&configuration.secrets)
+            .unwrap_or_else(|e| {
+                let message = e.to_string();
+                tracing::event!(tracing::Level::ERROR, message);
+                panic!("{}", message);
+            });
+        match resolve_secrets(secret_provider.as_ref(), &configuration.secrets).await {
+            Ok(secrets) => configuration.secrets.store.replace(secrets),
+            Err(e) => {
+                let message = format!("Couldn't resolve secrets!: {}", e);
+                tracing::event!(tracing::Level::ERROR, message);
+                panic!("{}", message);
+            }
+        }
+        let secret_refresh =
+            configuration
+                .secrets
+                .refresh_interval_in_seconds
+                .map(|refresh_interval_in_seconds| {
+                    SecretRefresh::start(
+                        secret_provider,
+                        configuration.secrets.clone(),
+                        time::Duration::from_secs(refresh_interval_in_seconds),
+                    )
+                });
+
         let connection_pool = if let Some(pool) = pool {
             pool
         } else {
@@ -67,34 +103,200 @@ impl Application {
             configuration.application.host, configuration.application.port
         );
 
+        let shared_pool = match configuration.tenant_pools.strategy {
+            TenantPoolStrategy::PerTenant => None,
+            strategy => Some(SharedTenantPool::new(
+                connect_shared_tenant_pool(&configuration).await,
+                strategy,
+            )),
+        };
+
+        let app_state_data = Data::new(AppState {
+            pools: TenantPoolRegistry::with_limits(TenantPoolLimits {
+                max_pools: configuration.tenant_pools.max_pools,
+                max_total_connections: configuration.tenant_pools.max_total_connections,
+            })
+            .with_drain_timeout(time::Duration::from_secs(
+                configuration.tenant_pools.drain_timeout_in_seconds,
+            )),
+            shared_pool,
+        });
+
+        // A single janitor for the whole application, not one per actix worker
+        let tenant_pool_janitor = TenantPoolJanitor::start(
+            app_state_data.clone(),
+            TenantPoolJanitorSettings::from(&configuration.tenant_pools),
+        );
+
+        let tenant_credentials_rotation = configuration
+            .tenant_pools
+            .credentials_rotation
+            .as_ref()
+            .map(|rotation_configuration| {
+                TenantCredentialsRotation::start(
+                    app_state_data.clone(),
+                    connection_pool.clone(),
+                    configuration.clone(),
+                    TenantCredentialsRotationSettings::from(rotation_configuration),
+                )
+            });
+
+        let tenant_deletion = TenantDeletion::start(
+            app_state_data.clone(),
+            connection_pool.clone(),
+            TenantDeletionSettings::from(&configuration.tenant_pools),
+        );
+
+        // Warm-up runs in the background so that a slow or failing tenant never blocks startup
+        tokio::spawn(warm_up_tenant_pools(
+            app_state_data.clone(),
+            connection_pool.clone(),
+            configuration.clone(),
+        ));
+
         let listener = TcpListener::bind(&address)?;
         let port = listener.local_addr()?.port();
-        let server = run(listener, connection_pool, configuration).await?;
+        let server = run(
+            listener,
+            connection_pool,
+            app_state_data,
+            tenant_pool_janitor.handle(),
+            configuration,
+        )
+        .await?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            tenant_pool_janitor,
+            tenant_credentials_rotation,
+            tenant_deletion,
+            secret_refresh,
+        })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
 
+    /// Runs the server until it receives a shutdown signal, then stops the background tasks.
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        let result = self.server.await;
+        self.tenant_pool_janitor.shutdown().await;
+        if let Some(tenant_credentials_rotation) = self.tenant_credentials_rotation {
+            tenant_credentials_rotation.shutdown().await;
+        }
+        self.tenant_deletion.shutdown().await;
+        if let Some(secret_refresh) = self.secret_refresh {
+            secret_refresh.shutdown().await;
+        }
+
+        result
+    }
+}
+
+/// Pool of the switcher role used by the shared tenant pool strategies.
+async fn connect_shared_tenant_pool(configuration: &Configuration) -> PgPool {
+    let shared_pool_configuration = match &configuration.tenant_pools.shared_pool {
+        Some(shared_pool_configuration) => shared_pool_configuration,
+        None => {
+            let message =
+                "`tenant_pools.shared_pool` is required by the shared tenant pool strategies";
+            tracing::event!(target: "sqlx", tracing::Level::ERROR, message);
+            panic!("{}", message);
+        }
+    };
+
+    let connect_options = configuration
+        .database
+        .with_db()
+        .username(&shared_pool_configuration.username)
+        .password(shared_pool_configuration.password.expose_secret());
+    let mut pool_options = PgPoolOptions::new();
+
+    if let Some(max_connections) = shared_pool_configuration
+        .max_connections
+        .or(configuration.database.max_connections)
+    {
+        pool_options = pool_options.max_connections(max_connections);
+    }
+
+    match pool_options.connect_with(connect_options).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            let message = format!(
+                "Couldn't establish shared tenant pool connection!: {:#?}",
+                e
+            );
+            tracing::event!(target: "sqlx", tracing::Level::ERROR, message);
+            panic!("{}", message);
+        }
     }
 }
 
 async fn run(
     listener: TcpListener,
     db_pool: PgPool,
+    app_state_data: Data<AppState>,
+    tenant_pool_janitor: TenantPoolJanitorHandle,
     configuration: Configuration,
 ) -> Result<Server, std::io::Error> {
     let configuration_data = Data::new(configuration.clone());
-    let app_state_data = Data::new(AppState {
-        pools: Arc::new(Mutex::new(HashMap::new())),
-    });
+    let tenant_pool_janitor_data = Data::new(tenant_pool_janitor);
     let database_pool_data = Data::new(db_pool);
+    let tenant_resolver_data = Data::new(
+        TenantResolverChain::from_configuration(&configuration.tenant_resolution).unwrap_or_else(
+            |message| {
+                tracing::event!(tracing::Level::ERROR, message);
+                panic!("{}", message);
+            },
+        ),
+    );
+    // Tenant passwords couldn't be encrypted, fail right away instead of at the first tenant created
+    if let Err(message) = configuration.secrets.current().active_encryption_key() {
+        tracing::event!(tracing::Level::ERROR, message);
+        panic!("{}", message);
+    }
+    let session_backend = match configuration.application.session_store {
+        SessionStoreBackend::Cookie => SessionBackend::Cookie,
+        SessionStoreBackend::Redis => SessionBackend::Redis(Box::new(
+            RedisSessions::from_configuration(
+                &configuration.redis,
+                &configuration.tenant_resolution.session_key,
+            )
+            .await
+            .unwrap_or_else(|message| {
+                tracing::event!(tracing::Level::ERROR, message);
+                panic!("{}", message);
+            }),
+        )),
+    };
+    let session_backend_data = Data::new(session_backend.clone());
+    let internal_network_guard = InternalNetworkGuard::from_configuration(
+        &configuration.internal_network,
+    )
+    .unwrap_or_else(|message| {
+        tracing::event!(tracing::Level::ERROR, message);
+        panic!("{}", message);
+    });
+    // Tenants are also addressable as `/t/{tenant}/...` when resolved from the path
+    let tenant_path_scope = configuration
+        .tenant_resolution
+        .strategies
+        .contains(&TenantResolutionStrategy::Path)
+        .then(|| {
+            PathTenantResolver::new(&configuration.tenant_resolution.path_prefix).scope_path()
+        });
     let server = HttpServer::new(move || {
-        let hmac_secret_key = Key::from(configuration.secrets.hmac.expose_secret().as_bytes());
+        // Taken once per worker, sessions are signed with the same key until the next restart
+        let hmac_secret_key = Key::from(
+            configuration
+                .secrets
+                .current()
+                .hmac
+                .expose_secret()
+                .as_bytes(),
+        );
 
         let application_host_origin = configuration.application.host.clone();
         let application_cookie = configuration.application.cookie.clone();
@@ -106,13 +308,13 @@ async fn run(
                     .as_bytes()
                     .ends_with(application_host_origin.as_bytes())
             })
-            .allowed_methods(vec!["GET", "POST", "PUT"])
+            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
             .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
             .allowed_header(http::header::CONTENT_TYPE)
             .max_age(3600);
 
-        let actix_cookie_session_middleware =
-            SessionMiddleware::builder(CookieSessionStore::default(), hmac_secret_key)
+        let actix_session_middleware =
+            SessionMiddleware::builder(session_backend.clone(), hmac_secret_key)
                 .cookie_http_only(application_cookie.http_only)
                 .cookie_same_site(application_cookie.same_site.try_into().unwrap())
                 .cookie_secure(application_cookie.secure)
@@ -126,33 +328,38 @@ async fn run(
                 )
                 .build();
 
-        let actix_internal_network_guard = InternalNetworkGuard::new();
-        let state_clone = app_state_data.clone();
-
-        tokio::spawn(async move {
-            let cleanup_interval = tokio::time::Duration::from_secs(5 * 60); // 5 minutes
-            let idle_duration_in_seconds = 10 * 60; // 10 minutes
-
-            loop {
-                time::sleep(cleanup_interval).await;
-                cleanup_idle_tenant_pools(&state_clone, idle_duration_in_seconds).await;
-            }
-        });
-
         actix_web::App::new()
             .app_data(app_state_data.clone())
             .app_data(database_pool_data.clone())
             .app_data(configuration_data.clone())
-            .wrap(actix_cookie_session_middleware)
+            .app_data(tenant_pool_janitor_data.clone())
+            .app_data(tenant_resolver_data.clone())
+            .app_data(session_backend_data.clone())
+            // Verifies keys sent to any route, routes requiring one or a scope wrap their own
+            .wrap(ApiKeyAuthentication::new())
+            .wrap(actix_session_middleware)
             .wrap(actix_compress_middleware)
             .wrap(actix_cors_middleware)
             .route("/health_check", web::get().to(health_check))
             .service(web::scope("/v1").configure(public::configure))
             .service(
                 web::scope("/internal")
-                    .wrap(actix_internal_network_guard)
+                    .wrap(internal_network_guard.clone())
                     .configure(internal::configure),
             )
+            .configure(|cfg| {
+                if let Some(tenant_path_scope) = &tenant_path_scope {
+                    cfg.service(
+                        web::scope(tenant_path_scope)
+                            .service(web::scope("/v1").configure(public::configure))
+                            .service(
+                                web::scope("/internal")
+                                    .wrap(internal_network_guard.clone())
+                                    .configure(internal::configure),
+                            ),
+                    );
+                }
+            })
             .wrap(TracingLogger::default())
     })
     .listen(listener)?
@@ -0,0 +1,319 @@
+use crate::configurations::Configuration;
+use crate::models::{ApiKeyScope, AppError, AuthenticatedApiKey, API_KEY_PREFIX};
+use crate::utils::{verify_api_key, TenantKey};
+use actix_web::dev::{self, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{web, Error, HttpMessage};
+use anyhow::anyhow;
+use secrecy::SecretString;
+use sqlx::PgPool;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// Middleware verifying the API key sent as `Authorization: Bearer tk_...`.
+///
+/// The verified key is stored in the request extensions, where the `api_key` tenant
+/// resolution strategy finds its tenant. Requests with an invalid key get a `401`, requests
+/// whose tenant header names another tenant than the key's get a `403`. Requests without a
+/// key get a `401` when the key is [`required`](Self::required) or a scope is, and are let
+/// through otherwise. Nested instances reuse the key verified by an outer one.
+#[derive(Clone, Debug, Default)]
+pub struct ApiKeyAuthentication {
+    required: bool,
+    scopes: Vec<ApiKeyScope>,
+}
+
+impl ApiKeyAuthentication {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn with_scope(mut self, scope: ApiKeyScope) -> Self {
+        self.scopes.push(scope);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ApiKeyAuthentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ApiKeyAuthenticationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiKeyAuthenticationMiddleware {
+            service: Rc::new(service),
+            authentication: Rc::new(self.clone()),
+        }))
+    }
+}
+
+pub struct ApiKeyAuthenticationMiddleware<S> {
+    service: Rc<S>,
+    authentication: Rc<ApiKeyAuthentication>,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiKeyAuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let authentication = self.authentication.clone();
+
+        Box::pin(async move {
+            authenticate(&req, &authentication).await?;
+
+            srv.call(req).await
+        })
+    }
+}
+
+async fn authenticate(
+    req: &ServiceRequest,
+    authentication: &ApiKeyAuthentication,
+) -> Result<(), AppError> {
+    let authenticated = req.extensions().get::<AuthenticatedApiKey>().cloned();
+    let authenticated = match authenticated {
+        Some(authenticated) => authenticated,
+        None => match bearer_api_key(req) {
+            Some(key) => {
+                let pool = req
+                    .app_data::<web::Data<PgPool>>()
+                    .ok_or_else(missing_app_data)?;
+                let configuration = req
+                    .app_data::<web::Data<Configuration>>()
+                    .ok_or_else(missing_app_data)?;
+
+                let authenticated = verify_api_key(&key, pool, configuration).await?;
+                ensure_tenant_header_matches(req, &authenticated, configuration)?;
+                req.extensions_mut().insert(authenticated.clone());
+
+                authenticated
+            }
+            // A scope can't be granted without a key
+            None if authentication.required || !authentication.scopes.is_empty() => {
+                return Err(AppError::AuthenticationFailed(
+                    "API key missing".to_string(),
+                ))
+            }
+            None => return Ok(()),
+        },
+    };
+
+    if let Some(scope) = authentication
+        .scopes
+        .iter()
+        .find(|scope| !authenticated.api_key.has_scope(**scope))
+    {
+        return Err(AppError::AuthorizationFailed(format!(
+            "API key lacks the {} scope",
+            scope.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+fn bearer_api_key(req: &ServiceRequest) -> Option<SecretString> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.starts_with(API_KEY_PREFIX))
+        .map(SecretString::from)
+}
+
+/// The key decides the tenant, a tenant header naming another one is a mistake or an attack.
+fn ensure_tenant_header_matches(
+    req: &ServiceRequest,
+    authenticated: &AuthenticatedApiKey,
+    configuration: &Configuration,
+) -> Result<(), AppError> {
+    let header_name = configuration.tenant_resolution.header_name.as_str();
+    let Some(value) = req.headers().get(header_name) else {
+        return Ok(());
+    };
+
+    let matches = match value.to_str().map(|value| TenantKey::parse(value.trim())) {
+        Ok(TenantKey::Id(tenant_id)) => tenant_id == authenticated.api_key.tenant_id,
+        Ok(TenantKey::Name(name)) => name == authenticated.tenant_name,
+        Err(_) => false,
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(AppError::AuthorizationFailed(format!(
+            "{} does not match the API key's tenant",
+            header_name
+        )))
+    }
+}
+
+fn missing_app_data() -> AppError {
+    AppError::InternalError(anyhow!(
+        "ApiKeyAuthentication requires the database pool and configuration"
+    ))
+}
+
+// These run against the development database: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::utils::{create_api_key, revoke_api_key, NewApiKey};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App, HttpResponse};
+    use chrono::{Duration, Utc};
+    use secrecy::ExposeSecret;
+    use sqlx::postgres::PgPoolOptions;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_api_key_authentication() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let (tenant_id, tenant_name): (Uuid, String) =
+            sqlx::query_as("SELECT id, name FROM tenants ORDER BY created_at LIMIT 1")
+                .fetch_one(&pool)
+                .await
+                .expect("Expected at least one tenant.");
+
+        let new_api_key = |scopes, expires_at| NewApiKey {
+            name: "authentication test".to_string(),
+            scopes,
+            expires_at,
+        };
+        let (reader, reader_key) = create_api_key(
+            &tenant_id,
+            &new_api_key(
+                vec![ApiKeyScope::UsersRead],
+                Some(Utc::now() + Duration::hours(1)),
+            ),
+            &pool,
+            &configuration,
+        )
+        .await
+        .unwrap();
+        let (revoked, revoked_key) = create_api_key(
+            &tenant_id,
+            &new_api_key(vec![ApiKeyScope::UsersRead], None),
+            &pool,
+            &configuration,
+        )
+        .await
+        .unwrap();
+        revoke_api_key(&tenant_id, &revoked.id, &pool)
+            .await
+            .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(configuration))
+                .wrap(ApiKeyAuthentication::new())
+                .route(
+                    "/read",
+                    web::get().to(HttpResponse::Ok).wrap(
+                        ApiKeyAuthentication::new()
+                            .required()
+                            .with_scope(ApiKeyScope::UsersRead),
+                    ),
+                )
+                .route(
+                    "/write",
+                    web::get()
+                        .to(HttpResponse::Ok)
+                        .wrap(ApiKeyAuthentication::new().with_scope(ApiKeyScope::UsersWrite)),
+                ),
+        )
+        .await;
+
+        let tampered_key = format!("{}x", reader_key.expose_secret());
+        let other_tenant = Uuid::new_v4().to_string();
+        let cases: Vec<(&str, Option<&str>, Option<&str>, StatusCode)> = vec![
+            (
+                "/read",
+                Some(reader_key.expose_secret()),
+                None,
+                StatusCode::OK,
+            ),
+            (
+                "/read",
+                Some(reader_key.expose_secret()),
+                Some(tenant_name.as_str()),
+                StatusCode::OK,
+            ),
+            (
+                "/read",
+                Some(reader_key.expose_secret()),
+                Some(other_tenant.as_str()),
+                StatusCode::FORBIDDEN,
+            ),
+            ("/read", None, None, StatusCode::UNAUTHORIZED),
+            ("/read", Some(&tampered_key), None, StatusCode::UNAUTHORIZED),
+            (
+                "/read",
+                Some(revoked_key.expose_secret()),
+                None,
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                "/write",
+                Some(reader_key.expose_secret()),
+                None,
+                StatusCode::FORBIDDEN,
+            ),
+            ("/write", None, None, StatusCode::UNAUTHORIZED),
+        ];
+
+        for (i, (path, key, tenant_header, expected)) in cases.into_iter().enumerate() {
+            let mut req = test::TestRequest::get().uri(path);
+            if let Some(key) = key {
+                req = req.insert_header((header::AUTHORIZATION, format!("Bearer {}", key)));
+            }
+            if let Some(tenant_header) = tenant_header {
+                req = req.insert_header(("x-tenant-id", tenant_header));
+            }
+
+            let status = match test::try_call_service(&app, req.to_request()).await {
+                Ok(res) => res.status(),
+                Err(error) => error.as_response_error().status_code(),
+            };
+
+            assert_eq!(status, expected, "Case {}", i);
+        }
+
+        sqlx::query("DELETE FROM api_keys WHERE id = ANY($1)")
+            .bind(vec![reader.id, revoked.id])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use crate::configurations::Configuration;
+use crate::models::{ApiKey, ApiKeyScope, AppError, API_KEY_PREFIX};
+use crate::utils::{api_key_digest, generate_password};
+use chrono::{DateTime, Utc};
+use rand::distr::Alphanumeric;
+use rand::Rng;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const API_KEY_PREFIX_LENGTH: usize = 12;
+
+#[derive(Deserialize, Debug)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Keys without an expiry stay valid until revoked.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Creates an API key for the tenant, only the digest of the key is stored.
+///
+/// Returns the key itself next to its record, there is no way to get it back later.
+#[tracing::instrument(
+    name = "Creating API key.",
+    fields(tenant_id = %tenant_id),
+    skip(new_api_key, pool, configuration)
+)]
+pub async fn create_api_key(
+    tenant_id: &Uuid,
+    new_api_key: &NewApiKey,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<(ApiKey, SecretString), AppError> {
+    validate_new_api_key(new_api_key, Utc::now()).map_err(AppError::BadRequestError)?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM tenants WHERE id = $1)")
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFoundError);
+    }
+
+    let prefix: String = rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(API_KEY_PREFIX_LENGTH)
+        .map(char::from)
+        .collect();
+    let key = SecretString::from(format!(
+        "{}{}_{}",
+        API_KEY_PREFIX,
+        prefix,
+        generate_password().expose_secret()
+    ));
+    let key_hash = api_key_digest(&key, configuration)?;
+    let scopes: Vec<&str> = new_api_key
+        .scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect();
+
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        r#"
+        INSERT INTO api_keys (tenant_id, name, prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {}
+        "#,
+        ApiKey::COLUMNS
+    ))
+    .bind(tenant_id)
+    .bind(&new_api_key.name)
+    .bind(&prefix)
+    .bind(&key_hash)
+    .bind(&scopes)
+    .bind(new_api_key.expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!(api_key_id = %api_key.id, prefix, "Created API key");
+
+    Ok((api_key, key))
+}
+
+fn validate_new_api_key(new_api_key: &NewApiKey, now: DateTime<Utc>) -> Result<(), String> {
+    if new_api_key.name.trim().is_empty() || new_api_key.name.len() > 255 {
+        return Err("API key name must be between 1 and 255 characters".to_string());
+    }
+
+    if new_api_key.scopes.is_empty() {
+        return Err("API keys need at least one scope".to_string());
+    }
+
+    if new_api_key
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Err("API key expiry must be in the future".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_validate_new_api_key() {
+        let now = Utc::now();
+        let new_api_key = |name: &str, scopes: Vec<ApiKeyScope>, expires_at| NewApiKey {
+            name: name.to_string(),
+            scopes,
+            expires_at,
+        };
+
+        assert!(
+            validate_new_api_key(&new_api_key("ci", vec![ApiKeyScope::UsersRead], None), now)
+                .is_ok()
+        );
+        assert!(validate_new_api_key(
+            &new_api_key(
+                "ci",
+                vec![ApiKeyScope::UsersRead],
+                Some(now + Duration::days(1))
+            ),
+            now
+        )
+        .is_ok());
+
+        assert!(
+            validate_new_api_key(&new_api_key(" ", vec![ApiKeyScope::UsersRead], None), now)
+                .is_err()
+        );
+        assert!(validate_new_api_key(&new_api_key("ci", vec![], None), now).is_err());
+        assert!(validate_new_api_key(
+            &new_api_key("ci", vec![ApiKeyScope::UsersRead], Some(now)),
+            now
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,9 @@
+mod api_key_authentication;
+mod create_api_key;
+mod revoke_api_key;
+mod verify_api_key;
+
+pub use api_key_authentication::*;
+pub use create_api_key::*;
+pub use revoke_api_key::*;
+pub use verify_api_key::*;
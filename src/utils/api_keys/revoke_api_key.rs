@@ -0,0 +1,34 @@
+use crate::models::{ApiKey, AppError};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Revokes one of the tenant's API keys, revoking a key twice keeps its first revocation time.
+#[tracing::instrument(
+    name = "Revoking API key.",
+    fields(tenant_id = %tenant_id, api_key_id = %api_key_id),
+    skip(pool)
+)]
+pub async fn revoke_api_key(
+    tenant_id: &Uuid,
+    api_key_id: &Uuid,
+    pool: &PgPool,
+) -> Result<ApiKey, AppError> {
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        r#"
+        UPDATE api_keys
+        SET revoked_at = COALESCE(revoked_at, CURRENT_TIMESTAMP)
+        WHERE id = $1 AND tenant_id = $2
+        RETURNING {}
+        "#,
+        ApiKey::COLUMNS
+    ))
+    .bind(api_key_id)
+    .bind(tenant_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::NotFoundError)?;
+
+    tracing::info!("Revoked API key");
+
+    Ok(api_key)
+}
@@ -0,0 +1,92 @@
+use crate::configurations::Configuration;
+use crate::models::{ApiKey, AppError, AuthenticatedApiKey, API_KEY_PREFIX};
+use crate::utils::argon2_digest;
+use anyhow::anyhow;
+use argon2::Params;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::{FromRow, PgPool};
+use subtle::ConstantTimeEq;
+
+#[derive(FromRow)]
+struct ApiKeyRow {
+    #[sqlx(flatten)]
+    api_key: ApiKey,
+    key_hash: String,
+    tenant_name: String,
+}
+
+/// Checks a presented API key against its stored digest, its revocation and its expiry.
+///
+/// Every failure is reported as the same authentication error.
+#[tracing::instrument(name = "Verifying API key.", skip_all)]
+pub async fn verify_api_key(
+    key: &SecretString,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<AuthenticatedApiKey, AppError> {
+    let prefix = key
+        .expose_secret()
+        .strip_prefix(API_KEY_PREFIX)
+        .and_then(|rest| rest.split_once('_'))
+        .map(|(prefix, _)| prefix)
+        .ok_or_else(|| AppError::AuthenticationFailed("Malformed API key".to_string()))?;
+
+    let row = sqlx::query_as::<_, ApiKeyRow>(
+        r#"
+        SELECT api_keys.id, api_keys.tenant_id, api_keys.name, api_keys.prefix, api_keys.scopes,
+               api_keys.expires_at, api_keys.revoked_at, api_keys.created_at, api_keys.key_hash,
+               tenants.name AS tenant_name
+        FROM api_keys
+        JOIN tenants ON tenants.id = api_keys.tenant_id
+        WHERE api_keys.prefix = $1
+        "#,
+    )
+    .bind(prefix)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::AuthenticationFailed("Unknown API key".to_string()))?;
+
+    let key_hash = api_key_digest(key, configuration)?;
+    if !bool::from(key_hash.as_bytes().ct_eq(row.key_hash.as_bytes())) {
+        return Err(AppError::AuthenticationFailed(
+            "Invalid API key".to_string(),
+        ));
+    }
+
+    if row.api_key.revoked_at.is_some() {
+        return Err(AppError::AuthenticationFailed(
+            "Revoked API key".to_string(),
+        ));
+    }
+
+    if row
+        .api_key
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(AppError::AuthenticationFailed(
+            "Expired API key".to_string(),
+        ));
+    }
+
+    Ok(AuthenticatedApiKey {
+        api_key: row.api_key,
+        tenant_name: row.tenant_name,
+    })
+}
+
+/// Digest stored for an API key, see [`argon2_digest`].
+///
+/// Keys are random with ~190 bits of entropy, they don't need the memory hard cost meant for
+/// passwords, which keeps verifying a key on every request cheap.
+pub fn api_key_digest(
+    key: &SecretString,
+    configuration: &Configuration,
+) -> Result<String, AppError> {
+    let params = Params::new(1024, 1, 1, None).map_err(|e| AppError::InternalError(anyhow!(e)))?;
+    let secrets = configuration.secrets.current();
+
+    argon2_digest(key, &secrets.argon2_salt, &secrets.argon2_key, params)
+        .map_err(|e| AppError::InternalError(anyhow!(e)))
+}
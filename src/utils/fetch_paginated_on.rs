@@ -0,0 +1,118 @@
+use crate::utils::quote_identifier;
+use serde::Serialize;
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, PgConnection, Postgres};
+use sqlx_paginated::{PaginatedResponse, QueryBuilder, QueryParams, QuerySortDirection};
+
+/// Same as `PaginatedQueryBuilder::fetch_paginated`, but runs on a connection instead of a pool
+/// so that the queries can be part of a tenant transaction.
+pub async fn fetch_paginated_on<'q, T>(
+    connection: &mut PgConnection,
+    sql: &str,
+    params: impl Into<QueryParams<'q, T>>,
+) -> Result<PaginatedResponse<T>, sqlx::Error>
+where
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin + Serialize + Default + 'q,
+{
+    let params: QueryParams<T> = params.into();
+    let (count_sql, main_sql) = paginated_sql(sql, &params);
+
+    let (_, count_arguments) = build_conditions(&params);
+    let total: i64 = sqlx::query_scalar_with(&count_sql, count_arguments)
+        .fetch_one(&mut *connection)
+        .await?;
+    let total_pages = match total {
+        0 => 0,
+        _ => (total + params.pagination.page_size - 1) / params.pagination.page_size,
+    };
+
+    let (_, main_arguments) = build_conditions(&params);
+    let records = sqlx::query_as_with::<Postgres, T, _>(&main_sql, main_arguments)
+        .fetch_all(&mut *connection)
+        .await?;
+
+    Ok(PaginatedResponse {
+        records,
+        pagination: Some(params.pagination.clone()),
+        total: Some(total),
+        total_pages: Some(total_pages),
+    })
+}
+
+// Same safe defaults (column protection included) as `PaginatedQueryBuilder`
+fn build_conditions<T>(params: &QueryParams<T>) -> (Vec<String>, sqlx::postgres::PgArguments)
+where
+    T: Serialize + Default,
+{
+    QueryBuilder::<T, Postgres>::new()
+        .with_search(params)
+        .with_filters(params)
+        .with_date_range(params)
+        .build()
+}
+
+fn paginated_sql<T>(sql: &str, params: &QueryParams<T>) -> (String, String)
+where
+    T: Serialize + Default,
+{
+    let (conditions, _) = build_conditions(params);
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    let base_sql = format!("WITH base_query AS ({})", sql);
+
+    let count_sql = format!(
+        "{} SELECT COUNT(*) FROM base_query{}",
+        base_sql, where_clause
+    );
+
+    let order = match params.sort.sort_direction {
+        QuerySortDirection::Ascending => "ASC",
+        QuerySortDirection::Descending => "DESC",
+    };
+    let offset = (params.pagination.page - 1) * params.pagination.page_size;
+    let main_sql = format!(
+        "{} SELECT * FROM base_query{} ORDER BY {} {} LIMIT {} OFFSET {}",
+        base_sql,
+        where_clause,
+        quote_identifier(&params.sort.sort_column),
+        order,
+        params.pagination.page_size,
+        offset
+    );
+
+    (count_sql, main_sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx_paginated::QueryParamsBuilder;
+
+    #[derive(Serialize, Default)]
+    struct Item {
+        name: String,
+        created_at: Option<chrono::DateTime<chrono::Utc>>,
+    }
+
+    #[test]
+    fn test_paginated_sql() {
+        let params = QueryParamsBuilder::<Item>::new()
+            .with_pagination(3, 20)
+            .with_sort("name", QuerySortDirection::Ascending)
+            .build();
+
+        let (count_sql, main_sql) = paginated_sql("SELECT * FROM items", &params);
+
+        assert_eq!(
+            count_sql,
+            "WITH base_query AS (SELECT * FROM items) SELECT COUNT(*) FROM base_query"
+        );
+        assert_eq!(
+            main_sql,
+            "WITH base_query AS (SELECT * FROM items) SELECT * FROM base_query ORDER BY \"name\" ASC LIMIT 20 OFFSET 40"
+        );
+    }
+}
@@ -1,17 +1,29 @@
+use crate::configurations::{ForwardedHeader, InternalNetworkConfiguration};
 use actix_web::{
     dev::{self, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderMap, FORWARDED, X_FORWARDED_FOR},
     Error,
 };
+use ipnet::IpNet;
 use std::pin::Pin;
 use std::{
     future::{ready, Ready},
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     rc::Rc,
     str::FromStr,
 };
 
+/// Restricts a scope to clients from trusted networks.
+///
+/// The client is the peer of the connection. Only when that peer is a trusted proxy is the
+/// forwarded header (`X-Forwarded-For` unless configured otherwise) followed, from the right,
+/// up to the first address that isn't a trusted proxy itself. Anyone else can put whatever
+/// they like into those headers.
+#[derive(Clone)]
 pub struct InternalNetworkGuard {
-    trusted_networks: Vec<String>,
+    trusted_networks: Vec<IpNet>,
+    trusted_proxies: Vec<IpNet>,
+    forwarded_header: ForwardedHeader,
     trusted_headers: Vec<String>,
 }
 
@@ -24,15 +36,20 @@ impl Default for InternalNetworkGuard {
 impl InternalNetworkGuard {
     pub fn new() -> Self {
         InternalNetworkGuard {
-            // Default trusted networks (localhost, private networks)
-            trusted_networks: vec![
-                "127.0.0.1".to_string(), // localhost
-                "::1".to_string(),       // IPv6 localhost
-                "10.".to_string(),       // Private Class A
-                "172.16.".to_string(),   // Private Class B start
-                "172.31.".to_string(),   // Private Class B end
-                "192.168.".to_string(),  // Private Class C
-            ],
+            // Default trusted networks (loopback, private networks)
+            trusted_networks: [
+                "127.0.0.0/8",    // localhost
+                "::1/128",        // IPv6 localhost
+                "10.0.0.0/8",     // Private Class A
+                "172.16.0.0/12",  // Private Class B
+                "192.168.0.0/16", // Private Class C
+                "fc00::/7",       // IPv6 unique local addresses
+            ]
+            .into_iter()
+            .map(|network| parse_network(network).unwrap())
+            .collect(),
+            trusted_proxies: vec![],
+            forwarded_header: ForwardedHeader::default(),
             // Headers that might indicate trusted internal service calls
             trusted_headers: vec![
                 "X-Internal-Request".to_string(),
@@ -40,32 +57,150 @@ impl InternalNetworkGuard {
             ],
         }
     }
-    pub fn with_networks(mut self, networks: Vec<String>) -> Self {
+
+    pub fn from_configuration(
+        configuration: &InternalNetworkConfiguration,
+    ) -> Result<Self, String> {
+        let parse_networks = |networks: &[String]| {
+            networks
+                .iter()
+                .map(|network| parse_network(network))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(InternalNetworkGuard {
+            trusted_networks: parse_networks(&configuration.trusted_networks)
+                .map_err(|e| format!("internal_network.trusted_networks: {}", e))?,
+            trusted_proxies: parse_networks(&configuration.trusted_proxies)
+                .map_err(|e| format!("internal_network.trusted_proxies: {}", e))?,
+            forwarded_header: configuration.forwarded_header,
+            trusted_headers: configuration.trusted_headers.clone(),
+        })
+    }
+
+    pub fn with_networks(mut self, networks: Vec<IpNet>) -> Self {
         self.trusted_networks.extend(networks);
         self
     }
+    pub fn with_proxies(mut self, proxies: Vec<IpNet>) -> Self {
+        self.trusted_proxies.extend(proxies);
+        self
+    }
+    pub fn with_forwarded_header(mut self, forwarded_header: ForwardedHeader) -> Self {
+        self.forwarded_header = forwarded_header;
+        self
+    }
     pub fn with_headers(mut self, headers: Vec<String>) -> Self {
         self.trusted_headers.extend(headers);
         self
     }
 
     /// Check if an IP address is in a trusted network
-    pub fn is_trusted_ip(&self, ip_str: &str) -> bool {
-        if let Ok(ip) = IpAddr::from_str(ip_str) {
-            if ip.to_string() == "127.0.0.1" || ip.to_string() == "::1" {
-                return true;
-            }
+    pub fn is_trusted_ip(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.trusted_networks
+            .iter()
+            .any(|network| network.contains(&ip))
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.trusted_proxies.iter().any(|proxy| proxy.contains(&ip))
+    }
 
-            for network in &self.trusted_networks {
-                if ip_str.starts_with(network) {
-                    return true;
-                }
+    /// The address of the client behind `peer_addr`, `None` when it can't be told.
+    pub fn client_ip(&self, peer_addr: Option<SocketAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let peer_ip = peer_addr?.ip().to_canonical();
+        if !self.is_trusted_proxy(peer_ip) {
+            return Some(peer_ip);
+        }
+
+        let mut client_ip = peer_ip;
+        for node in forwarded_chain(self.forwarded_header, headers).iter().rev() {
+            // Obfuscated or garbled entries leave the client unknown
+            client_ip = parse_node(node)?;
+            if !self.is_trusted_proxy(client_ip) {
+                break;
             }
         }
-        false
+
+        Some(client_ip)
+    }
+
+    fn is_trusted(&self, req: &ServiceRequest) -> bool {
+        let peer_addr = req.peer_addr();
+        let from_trusted_proxy = peer_addr.is_some_and(|addr| self.is_trusted_proxy(addr.ip()));
+
+        // Proxies are expected to drop these headers from outside requests
+        if from_trusted_proxy
+            && self
+                .trusted_headers
+                .iter()
+                .any(|header_name| req.headers().contains_key(header_name.as_str()))
+        {
+            return true;
+        }
+
+        self.client_ip(peer_addr, req.headers())
+            .is_some_and(|ip| self.is_trusted_ip(ip))
     }
 }
 
+/// Parses a network in CIDR notation, a single address is a network of its own.
+pub fn parse_network(network: &str) -> Result<IpNet, String> {
+    let network = network.trim();
+    let parsed = if network.contains('/') {
+        IpNet::from_str(network).ok().map(|network| network.trunc())
+    } else {
+        IpAddr::from_str(network).ok().map(IpNet::from)
+    };
+
+    parsed.ok_or_else(|| format!("Invalid network '{}'", network))
+}
+
+/// The forwarding chain of a request, the client first and the closest proxy last.
+/// Only `forwarded_header` is read, unlike `ConnectionInfo` which prefers `Forwarded`
+/// even when the proxies only append to `X-Forwarded-For`.
+fn forwarded_chain(forwarded_header: ForwardedHeader, headers: &HeaderMap) -> Vec<String> {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .flat_map(|value| value.to_str().unwrap_or("unknown").split(','))
+            .map(|node| node.trim().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    match forwarded_header {
+        ForwardedHeader::XForwardedFor => values(X_FORWARDED_FOR),
+        ForwardedHeader::Forwarded => values(FORWARDED)
+            .iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                    .map_or("unknown", |(_, node)| node.trim())
+                    .to_string()
+            })
+            .collect(),
+    }
+}
+
+/// Parses a node of a forwarding chain, `192.0.2.1`, `192.0.2.1:4711`, `2001:db8::1`
+/// or `"[2001:db8::1]:4711"`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    let ip = match node.strip_prefix('[') {
+        Some(bracketed) => IpAddr::from_str(bracketed.split_once(']')?.0).ok()?,
+        None => IpAddr::from_str(node).ok().or_else(|| {
+            let (ip, _port) = node.rsplit_once(':')?;
+            ip.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+        })?,
+    };
+
+    Some(ip.to_canonical())
+}
+
 impl<S, B> Transform<S, ServiceRequest> for InternalNetworkGuard
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
@@ -81,16 +216,14 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(InternalNetworkGuardMiddleware {
             service: Rc::new(service),
-            trusted_networks: self.trusted_networks.clone(),
-            trusted_headers: self.trusted_headers.clone(),
+            guard: Rc::new(self.clone()),
         }))
     }
 }
 
 pub struct InternalNetworkGuardMiddleware<S> {
     service: Rc<S>,
-    trusted_networks: Vec<String>,
-    trusted_headers: Vec<String>,
+    guard: Rc<InternalNetworkGuard>,
 }
 
 impl<S, B> Service<ServiceRequest> for InternalNetworkGuardMiddleware<S>
@@ -107,37 +240,7 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let srv = self.service.clone();
-        let trusted_headers = self.trusted_headers.clone();
-        let trusted_networks = self.trusted_networks.clone();
-
-        let mut is_trusted = false;
-
-        for header_name in &trusted_headers {
-            if req.headers().contains_key(header_name) {
-                is_trusted = true;
-                break;
-            }
-        }
-
-        // If not already trusted by headers, check IP address
-        if !is_trusted {
-            let remote_ip = req
-                .connection_info()
-                .realip_remote_addr()
-                .map(|addr| addr.to_string());
-
-            if let Some(addr) = remote_ip {
-                let ip = addr.split(':').next().unwrap_or(&addr);
-                let guard = InternalNetworkGuard {
-                    trusted_networks: trusted_networks.clone(),
-                    trusted_headers: vec![],
-                };
-
-                if guard.is_trusted_ip(ip) {
-                    is_trusted = true;
-                }
-            }
-        }
+        let is_trusted = self.guard.is_trusted(&req);
 
         Box::pin(async move {
             if is_trusted {
@@ -155,18 +258,285 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::InternalNetworkGuard;
+    use super::{parse_network, InternalNetworkGuard};
+    use crate::configurations::{get_configuration, ForwardedHeader};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{init_service, try_call_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use std::net::{IpAddr, SocketAddr};
 
     #[test]
     fn test_is_trusted_ip() {
         let guard = InternalNetworkGuard::new();
 
-        assert!(guard.is_trusted_ip("127.0.0.1"));
-        assert!(guard.is_trusted_ip("::1"));
-        assert!(guard.is_trusted_ip("10.0.0.1"));
-        assert!(guard.is_trusted_ip("192.168.1.1"));
+        let cases = [
+            ("127.0.0.1", true),
+            ("127.0.0.53", true),
+            ("::1", true),
+            ("10.0.0.1", true),
+            ("10.255.255.255", true),
+            ("172.16.0.1", true),
+            ("172.20.10.1", true),
+            ("172.31.255.255", true),
+            ("192.168.1.1", true),
+            ("fd12:3456:789a::1", true),
+            ("::ffff:10.0.0.1", true),
+            ("::ffff:127.0.0.1", true),
+            // Only sharing a string prefix with a trusted network
+            ("100.64.0.1", false),
+            ("101.0.0.1", false),
+            ("172.32.0.1", false),
+            ("172.15.255.255", false),
+            ("192.169.0.1", false),
+            ("8.8.8.8", false),
+            ("203.0.113.1", false),
+            ("::2", false),
+            ("2001:db8::1", false),
+            ("fe80::1", false),
+            ("::ffff:8.8.8.8", false),
+        ];
+
+        for (ip, expected) in cases {
+            assert_eq!(guard.is_trusted_ip(ip.parse().unwrap()), expected, "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_parse_network() {
+        let cases = [
+            ("10.0.0.0/8", Some("10.0.0.0/8")),
+            ("10.1.2.3/8", Some("10.0.0.0/8")),
+            (" 192.0.2.1 ", Some("192.0.2.1/32")),
+            ("2001:db8::/32", Some("2001:db8::/32")),
+            ("::1", Some("::1/128")),
+            ("10.0.0.0/33", None),
+            ("2001:db8::/129", None),
+            ("10.", None),
+            ("172.16.", None),
+            ("", None),
+        ];
+
+        for (network, expected) in cases {
+            assert_eq!(
+                parse_network(network)
+                    .ok()
+                    .map(|network| network.to_string()),
+                expected.map(str::to_string),
+                "{:?}",
+                network
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_configuration() {
+        let mut configuration = get_configuration().unwrap().internal_network;
+        assert_eq!(
+            configuration.forwarded_header,
+            ForwardedHeader::XForwardedFor
+        );
+        let guard = InternalNetworkGuard::from_configuration(&configuration).unwrap();
+        assert!(guard.is_trusted_ip("172.20.0.1".parse().unwrap()));
+        assert!(!guard.is_trusted_ip("100.64.0.1".parse().unwrap()));
+
+        configuration.trusted_proxies = vec!["10.0.0.5".to_string(), "proxy".to_string()];
+        let error = InternalNetworkGuard::from_configuration(&configuration)
+            .err()
+            .unwrap();
+        assert!(
+            error.contains("internal_network.trusted_proxies"),
+            "{}",
+            error
+        );
+    }
+
+    // Peer address, request headers, expected status
+    type Case = (&'static str, Vec<(&'static str, &'static str)>, StatusCode);
+
+    async fn assert_cases(forwarded_header: ForwardedHeader, cases: Vec<Case>) {
+        let guard = InternalNetworkGuard::new()
+            .with_proxies(vec![
+                parse_network("192.0.2.10").unwrap(),
+                parse_network("2001:db8::10").unwrap(),
+            ])
+            .with_forwarded_header(forwarded_header);
+        let app = init_service(
+            App::new()
+                .wrap(guard)
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for (i, (peer_addr, headers, expected)) in cases.into_iter().enumerate() {
+            let mut req = TestRequest::get().peer_addr(peer_addr.parse::<SocketAddr>().unwrap());
+            for header in headers {
+                req = req.append_header(header);
+            }
+
+            let status = match try_call_service(&app, req.to_request()).await {
+                Ok(res) => res.status(),
+                Err(error) => error.as_response_error().status_code(),
+            };
+
+            assert_eq!(status, expected, "{:?} case {}", forwarded_header, i);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_forwarded_headers_only_honored_from_trusted_proxies() {
+        assert_cases(
+            ForwardedHeader::XForwardedFor,
+            vec![
+                ("10.0.0.1:4000", vec![], StatusCode::OK),
+                ("[fd00::1]:4000", vec![], StatusCode::OK),
+                ("[::ffff:192.168.0.1]:4000", vec![], StatusCode::OK),
+                ("203.0.113.1:4000", vec![], StatusCode::FORBIDDEN),
+                ("[2001:db8::1]:4000", vec![], StatusCode::FORBIDDEN),
+                // Spoofed by a client talking to us directly
+                (
+                    "203.0.113.1:4000",
+                    vec![("X-Forwarded-For", "127.0.0.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "203.0.113.1:4000",
+                    vec![("Forwarded", "for=10.0.0.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "[2001:db8::1]:4000",
+                    vec![("X-Forwarded-For", "::1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "203.0.113.1:4000",
+                    vec![("X-Internal-Request", "true")],
+                    StatusCode::FORBIDDEN,
+                ),
+                // Forwarded by a trusted proxy
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Forwarded-For", "10.0.0.1")],
+                    StatusCode::OK,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Forwarded-For", "203.0.113.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "[2001:db8::10]:4000",
+                    vec![("X-Forwarded-For", "fd00::1")],
+                    StatusCode::OK,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Forwarded-For", "10.0.0.1, 2001:db8::10")],
+                    StatusCode::OK,
+                ),
+                // The client prepended a trusted address, the proxy appended the real one
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Forwarded-For", "127.0.0.1, 203.0.113.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Forwarded-For", "10.0.0.1, 203.0.113.1, 2001:db8::10")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![
+                        ("X-Forwarded-For", "127.0.0.1"),
+                        ("X-Forwarded-For", "203.0.113.1"),
+                    ],
+                    StatusCode::FORBIDDEN,
+                ),
+                // The client's `Forwarded` passed through by a proxy appending to `X-Forwarded-For`
+                (
+                    "192.0.2.10:4000",
+                    vec![
+                        ("Forwarded", "for=10.0.0.1"),
+                        ("X-Forwarded-For", "203.0.113.1"),
+                    ],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("Forwarded", "for=10.0.0.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Forwarded-For", "10.0.0.1.evil.example")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("X-Internal-Request", "true")],
+                    StatusCode::OK,
+                ),
+            ],
+        )
+        .await;
+
+        assert_cases(
+            ForwardedHeader::Forwarded,
+            vec![
+                (
+                    "192.0.2.10:4000",
+                    vec![("Forwarded", "for=\"[fd00::1]:4711\";proto=https")],
+                    StatusCode::OK,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("Forwarded", "for=10.0.0.1:4711, for=2001:db8::10")],
+                    StatusCode::OK,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("Forwarded", "for=10.0.0.1, for=203.0.113.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "203.0.113.1:4000",
+                    vec![("Forwarded", "for=10.0.0.1")],
+                    StatusCode::FORBIDDEN,
+                ),
+                // The client's `X-Forwarded-For` passed through by a proxy appending to `Forwarded`
+                (
+                    "192.0.2.10:4000",
+                    vec![
+                        ("X-Forwarded-For", "10.0.0.1"),
+                        ("Forwarded", "for=203.0.113.1"),
+                    ],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("Forwarded", "for=unknown")],
+                    StatusCode::FORBIDDEN,
+                ),
+                (
+                    "192.0.2.10:4000",
+                    vec![("Forwarded", "proto=https")],
+                    StatusCode::FORBIDDEN,
+                ),
+            ],
+        )
+        .await;
+    }
+
+    #[test]
+    fn test_client_ip_without_peer_address() {
+        let guard = InternalNetworkGuard::new();
+        let headers = TestRequest::get()
+            .insert_header(("X-Forwarded-For", "127.0.0.1"))
+            .to_http_request()
+            .headers()
+            .clone();
 
-        assert!(!guard.is_trusted_ip("8.8.8.8"));
-        assert!(!guard.is_trusted_ip("203.0.113.1"));
+        assert_eq!(guard.client_ip(None, &headers), None::<IpAddr>);
     }
 }
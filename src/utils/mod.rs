@@ -1,9 +1,23 @@
+mod api_keys;
+mod fetch_paginated_on;
 mod internal_network_guard;
+mod secrets;
 mod security;
+mod sessions;
 mod tenant_pool;
+mod tenant_resolution;
+mod tenant_transactions;
 mod tls;
+mod user_authentication;
 
+pub use api_keys::*;
+pub use fetch_paginated_on::*;
 pub use internal_network_guard::*;
+pub use secrets::*;
 pub use security::*;
+pub use sessions::*;
 pub use tenant_pool::*;
+pub use tenant_resolution::*;
+pub use tenant_transactions::*;
 pub use tls::*;
+pub use user_authentication::*;
@@ -0,0 +1,67 @@
+use crate::utils::{SecretFuture, SecretProvider, SecretProviderError};
+use secrecy::SecretString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Secrets read from `<directory>/<name>`, as Docker and Kubernetes mount them.
+///
+/// Trailing line breaks are dropped. Secrets without a file keep their configured value,
+/// so only some of them have to be mounted.
+pub struct FileSecretProvider {
+    directory: PathBuf,
+}
+
+impl FileSecretProvider {
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        FileSecretProvider {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl SecretProvider for FileSecretProvider {
+    fn resolve<'a>(&'a self, name: &'a str, configured: &'a SecretString) -> SecretFuture<'a> {
+        Box::pin(async move {
+            match tokio::fs::read_to_string(self.directory.join(name)).await {
+                Ok(mut contents) => {
+                    contents.truncate(contents.trim_end_matches(['\r', '\n']).len());
+                    Ok(SecretString::from(contents))
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(configured.clone()),
+                Err(source) => Err(SecretProviderError::Io {
+                    name: name.to_string(),
+                    source,
+                }),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+    use uuid::Uuid;
+
+    #[actix_web::test]
+    async fn test_file_secret_provider() {
+        let directory = std::env::temp_dir().join(format!("secrets-{}", Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        std::fs::write(directory.join("hmac"), "mounted\n").unwrap();
+        std::fs::create_dir(directory.join("argon2_key")).unwrap();
+        let provider = FileSecretProvider::new(&directory);
+        let configured = SecretString::from("configured");
+
+        let mounted = provider.resolve("hmac", &configured).await;
+        let missing = provider.resolve("argon2_salt", &configured).await;
+        let unreadable = provider.resolve("argon2_key", &configured).await;
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(mounted.unwrap().expose_secret(), "mounted");
+        assert_eq!(missing.unwrap().expose_secret(), "configured");
+        assert!(matches!(
+            unreadable,
+            Err(SecretProviderError::Io { name, .. }) if name == "argon2_key"
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+mod config_secret_provider;
+mod file_secret_provider;
+mod secret_provider;
+mod secret_refresh;
+mod vault_transit_secret_provider;
+#[cfg(any(test, feature = "vault-transit-stand-in"))]
+mod vault_transit_stand_in;
+
+pub use config_secret_provider::*;
+pub use file_secret_provider::*;
+pub use secret_provider::*;
+pub use secret_refresh::*;
+pub use vault_transit_secret_provider::*;
+#[cfg(any(test, feature = "vault-transit-stand-in"))]
+pub use vault_transit_stand_in::*;
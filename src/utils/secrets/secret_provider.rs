@@ -0,0 +1,146 @@
+use crate::configurations::{
+    validate_encryption_key_id, KeyringConfiguration, SecretProviderKind, Secrets,
+    SecretsConfiguration,
+};
+use crate::utils::{ConfigSecretProvider, FileSecretProvider, VaultTransitSecretProvider};
+use secrecy::SecretString;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SecretProviderError {
+    #[error("Invalid secret provider configuration: {0}")]
+    Configuration(String),
+    #[error("Secret {name} couldn't be read: {source}")]
+    Io {
+        name: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Secret {name} couldn't be decrypted by Vault: {message}")]
+    Vault { name: String, message: String },
+    #[error("Invalid secret {0}")]
+    InvalidSecret(String),
+}
+
+pub type SecretFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<SecretString, SecretProviderError>> + Send + 'a>>;
+
+/// Where the secrets of [`SecretsConfiguration`] come from, see `secrets.provider`.
+pub trait SecretProvider: Send + Sync {
+    /// Resolves the secret `name`, `configured` being its value in `secrets`.
+    fn resolve<'a>(&'a self, name: &'a str, configured: &'a SecretString) -> SecretFuture<'a>;
+}
+
+/// The provider `secrets.provider` names.
+pub fn secret_provider_from_configuration(
+    configuration: &SecretsConfiguration,
+) -> Result<Arc<dyn SecretProvider>, SecretProviderError> {
+    Ok(match configuration.provider {
+        SecretProviderKind::Config => Arc::new(ConfigSecretProvider),
+        SecretProviderKind::File => {
+            let file_configuration = configuration.file.as_ref().ok_or_else(|| {
+                SecretProviderError::Configuration(
+                    "`secrets.file` is required by the file provider".to_string(),
+                )
+            })?;
+            Arc::new(FileSecretProvider::new(&file_configuration.directory))
+        }
+        SecretProviderKind::VaultTransit => {
+            let vault_transit_configuration =
+                configuration.vault_transit.as_ref().ok_or_else(|| {
+                    SecretProviderError::Configuration(
+                        "`secrets.vault_transit` is required by the vault_transit provider"
+                            .to_string(),
+                    )
+                })?;
+            Arc::new(VaultTransitSecretProvider::new(
+                vault_transit_configuration,
+            )?)
+        }
+    })
+}
+
+/// Resolves every secret of `configuration` with `provider`, keyring keys being named
+/// `keyring.<key id>`, and decodes the encryption keys.
+pub async fn resolve_secrets(
+    provider: &dyn SecretProvider,
+    configuration: &SecretsConfiguration,
+) -> Result<Secrets, SecretProviderError> {
+    let mut keys = HashMap::with_capacity(configuration.keyring.keys.len());
+    for (key_id, key) in &configuration.keyring.keys {
+        validate_encryption_key_id(key_id).map_err(SecretProviderError::Configuration)?;
+        let name = format!("keyring.{}", key_id);
+        keys.insert(key_id.clone(), provider.resolve(&name, key).await?);
+    }
+
+    Secrets::new(
+        provider
+            .resolve("argon2_salt", &configuration.argon2_salt)
+            .await?,
+        provider
+            .resolve("argon2_key", &configuration.argon2_key)
+            .await?,
+        provider.resolve("hmac", &configuration.hmac).await?,
+        provider
+            .resolve("aes256_gcm_key", &configuration.aes256_gcm_key)
+            .await?,
+        KeyringConfiguration {
+            active_key_id: configuration.keyring.active_key_id.clone(),
+            keys,
+        },
+    )
+    .map_err(SecretProviderError::InvalidSecret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use secrecy::ExposeSecret;
+
+    const KEY_HEX: &str = "a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90";
+
+    #[actix_web::test]
+    async fn test_resolve_secrets() {
+        let mut configuration = get_configuration().expect("Failed to read configuration.");
+        configuration.secrets.keyring.keys =
+            HashMap::from([("2026-10".to_string(), SecretString::from(KEY_HEX))]);
+
+        let secrets = resolve_secrets(&ConfigSecretProvider, &configuration.secrets)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            secrets.hmac.expose_secret(),
+            configuration.secrets.hmac.expose_secret()
+        );
+        assert!(secrets.encryption_key(Some("2026-10")).is_ok());
+
+        for (key_id, key) in [("../hmac", KEY_HEX), ("2026-10", "a1b2c3")] {
+            configuration.secrets.keyring.keys =
+                HashMap::from([(key_id.to_string(), SecretString::from(key))]);
+            assert!(
+                resolve_secrets(&ConfigSecretProvider, &configuration.secrets)
+                    .await
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_secret_provider_from_configuration() {
+        let mut configuration = get_configuration().expect("Failed to read configuration.");
+
+        for provider in [SecretProviderKind::File, SecretProviderKind::VaultTransit] {
+            configuration.secrets.provider = provider;
+            assert!(matches!(
+                secret_provider_from_configuration(&configuration.secrets),
+                Err(SecretProviderError::Configuration(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::configurations::{Secrets, SecretsConfiguration};
+use crate::utils::{resolve_secrets, SecretProvider};
+use secrecy::ExposeSecret;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Background task resolving the secrets again every `interval`, see [`resolve_secrets`].
+///
+/// Failures are logged and keep the secrets resolved last. `hmac` is left out, sessions are
+/// signed with the key taken at startup until the next restart.
+pub struct SecretRefresh {
+    shutdown: watch::Sender<bool>,
+    worker: JoinHandle<()>,
+}
+
+impl SecretRefresh {
+    pub fn start(
+        provider: Arc<dyn SecretProvider>,
+        configuration: SecretsConfiguration,
+        interval: Duration,
+    ) -> Self {
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let worker = tokio::spawn(refresh_loop(
+            provider,
+            configuration,
+            interval,
+            shutdown_receiver,
+        ));
+
+        tracing::info!(
+            interval_in_seconds = interval.as_secs(),
+            "Started secret refresh"
+        );
+
+        SecretRefresh { shutdown, worker }
+    }
+
+    /// Signals the task to stop and waits for it to finish its current run.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+
+        if let Err(e) = self.worker.await {
+            tracing::error!("Secret refresh did not shut down cleanly: {}", e);
+        }
+
+        tracing::info!("Stopped secret refresh");
+    }
+}
+
+async fn refresh_loop(
+    provider: Arc<dyn SecretProvider>,
+    configuration: SecretsConfiguration,
+    interval: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(interval);
+    // Resolved at startup already
+    interval.tick().await;
+    // Reported once, every later run would resolve the same changed value again
+    let mut hmac_change_reported = false;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            // Also fires when the sender is gone
+            _ = shutdown.changed() => break,
+        }
+
+        match resolve_secrets(provider.as_ref(), &configuration).await {
+            Ok(mut secrets) => {
+                let current = configuration.current();
+                if !hmac_change_reported
+                    && current.hmac.expose_secret() != secrets.hmac.expose_secret()
+                {
+                    hmac_change_reported = true;
+                    tracing::warn!("The hmac secret changed, it is only taken after a restart");
+                }
+                secrets.hmac = current.hmac.clone();
+                let changed = changed_secrets(&current, &secrets);
+                if !changed.is_empty() {
+                    tracing::info!(changed = ?changed, "Refreshed secrets");
+                }
+                configuration.store.replace(secrets);
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to refresh secrets, keeping the current ones");
+            }
+        }
+    }
+}
+
+/// Names of the secrets that differ, never their values.
+fn changed_secrets(current: &Secrets, resolved: &Secrets) -> Vec<String> {
+    let mut changed = [
+        ("argon2_salt", &current.argon2_salt, &resolved.argon2_salt),
+        ("argon2_key", &current.argon2_key, &resolved.argon2_key),
+        ("hmac", &current.hmac, &resolved.hmac),
+        (
+            "aes256_gcm_key",
+            &current.aes256_gcm_key,
+            &resolved.aes256_gcm_key,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, current, resolved)| current.expose_secret() != resolved.expose_secret())
+    .map(|(name, _, _)| name.to_string())
+    .collect::<Vec<_>>();
+
+    for (key_id, key) in &resolved.keyring.keys {
+        let unchanged = current
+            .keyring
+            .keys
+            .get(key_id)
+            .is_some_and(|current| current.expose_secret() == key.expose_secret());
+        if !unchanged {
+            changed.push(format!("keyring.{}", key_id));
+        }
+    }
+    for key_id in current.keyring.keys.keys() {
+        if !resolved.keyring.keys.contains_key(key_id) {
+            changed.push(format!("keyring.{}", key_id));
+        }
+    }
+    changed.sort();
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::utils::FileSecretProvider;
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    const KEY_HEX: &str = "a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90";
+
+    #[actix_web::test]
+    async fn test_secret_refresh() {
+        let directory = std::env::temp_dir().join(format!("secrets-{}", Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        std::fs::write(directory.join("keyring.2026-10"), KEY_HEX).unwrap();
+        let mut configuration = get_configuration().expect("Failed to read configuration.");
+        configuration.secrets.keyring.keys =
+            [("2026-10".to_string(), SecretString::default())].into();
+        let provider: Arc<dyn SecretProvider> = Arc::new(FileSecretProvider::new(&directory));
+        let before = configuration.secrets.current();
+
+        let secret_refresh = SecretRefresh::start(
+            provider,
+            configuration.secrets.clone(),
+            Duration::from_millis(20),
+        );
+        std::fs::write(directory.join("hmac"), "rotated").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        secret_refresh.shutdown().await;
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let after = configuration.secrets.current();
+        assert_eq!(after.hmac.expose_secret(), before.hmac.expose_secret());
+        assert_eq!(after.keyring.keys["2026-10"].expose_secret(), KEY_HEX);
+        assert!(after.encryption_key(Some("2026-10")).is_ok());
+        assert_eq!(
+            changed_secrets(&before, &after),
+            vec!["keyring.2026-10".to_string()]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::configurations::VaultTransitConfiguration;
+use crate::utils::{SecretFuture, SecretProvider, SecretProviderError};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const VAULT_CIPHERTEXT_PREFIX: &str = "vault:";
+
+/// Configured secrets are ciphertexts of a Vault transit key, e.g. `vault:v1:...`, decrypted
+/// through `POST /v1/<mount>/decrypt/<key name>`. The key itself never leaves Vault, only the
+/// secrets it wraps are stored in the configuration.
+///
+/// Secrets configured empty stay empty.
+pub struct VaultTransitSecretProvider {
+    client: reqwest::Client,
+    decrypt_url: String,
+    token: SecretString,
+}
+
+#[derive(Serialize)]
+struct TransitDecryptRequest<'a> {
+    ciphertext: &'a str,
+}
+
+#[derive(Deserialize)]
+struct TransitResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct TransitDecryptResponse {
+    plaintext: SecretString,
+}
+
+#[derive(Deserialize, Default)]
+struct TransitErrorResponse {
+    #[serde(default)]
+    errors: Vec<String>,
+}
+
+impl VaultTransitSecretProvider {
+    pub fn new(configuration: &VaultTransitConfiguration) -> Result<Self, SecretProviderError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(configuration.timeout_in_seconds))
+            .build()
+            .map_err(|e| SecretProviderError::Configuration(e.to_string()))?;
+
+        Ok(VaultTransitSecretProvider {
+            client,
+            decrypt_url: format!(
+                "{}/v1/{}/decrypt/{}",
+                configuration.address.trim_end_matches('/'),
+                configuration.mount.trim_matches('/'),
+                configuration.key_name
+            ),
+            token: configuration.token.clone(),
+        })
+    }
+
+    async fn decrypt(&self, name: &str, ciphertext: &str) -> Result<SecretString, String> {
+        if !ciphertext.starts_with(VAULT_CIPHERTEXT_PREFIX) {
+            return Err("not a Vault transit ciphertext".to_string());
+        }
+
+        let response = self
+            .client
+            .post(&self.decrypt_url)
+            .header("X-Vault-Token", self.token.expose_secret())
+            .json(&TransitDecryptRequest { ciphertext })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_response = response
+                .json::<TransitErrorResponse>()
+                .await
+                .unwrap_or_default();
+            return Err(format!("{} {}", status, error_response.errors.join(", ")));
+        }
+
+        let decrypted = response
+            .json::<TransitResponse<TransitDecryptResponse>>()
+            .await
+            .map_err(|e| e.to_string())?;
+        let plaintext = BASE64_STANDARD
+            .decode(decrypted.data.plaintext.expose_secret())
+            .map_err(|e| e.to_string())?;
+
+        String::from_utf8(plaintext)
+            .map(SecretString::from)
+            .map_err(|_| format!("{} is not valid UTF-8", name))
+    }
+}
+
+impl SecretProvider for VaultTransitSecretProvider {
+    fn resolve<'a>(&'a self, name: &'a str, configured: &'a SecretString) -> SecretFuture<'a> {
+        Box::pin(async move {
+            if configured.expose_secret().is_empty() {
+                return Ok(configured.clone());
+            }
+
+            self.decrypt(name, configured.expose_secret())
+                .await
+                .map_err(|message| SecretProviderError::Vault {
+                    name: name.to_string(),
+                    message,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::VaultTransitStandIn;
+    use std::net::TcpListener;
+
+    const STAND_IN_KEY_HEX: &str =
+        "a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90";
+
+    fn configuration(address: &str, token: &str, key_name: &str) -> VaultTransitConfiguration {
+        VaultTransitConfiguration {
+            address: address.to_string(),
+            token: SecretString::from(token),
+            mount: "transit".to_string(),
+            key_name: key_name.to_string(),
+            timeout_in_seconds: 5,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_vault_transit_secret_provider() {
+        let (stand_in, server) = VaultTransitStandIn::start(
+            TcpListener::bind("127.0.0.1:0").unwrap(),
+            "transit",
+            "app",
+            SecretString::from("test-token"),
+            SecretString::from(STAND_IN_KEY_HEX),
+        )
+        .unwrap();
+        actix_web::rt::spawn(server);
+
+        let wrapped = SecretString::from(stand_in.encrypt(&SecretString::from("hunter2")).unwrap());
+        let provider = VaultTransitSecretProvider::new(&configuration(
+            stand_in.address(),
+            "test-token",
+            "app",
+        ))
+        .unwrap();
+
+        assert!(wrapped.expose_secret().starts_with("vault:v1:"));
+        assert_eq!(
+            provider
+                .resolve("hmac", &wrapped)
+                .await
+                .unwrap()
+                .expose_secret(),
+            "hunter2"
+        );
+        assert_eq!(
+            provider
+                .resolve("hmac", &SecretString::default())
+                .await
+                .unwrap()
+                .expose_secret(),
+            ""
+        );
+
+        // Not wrapped at all, and wrapped by another key
+        let cases = [("hunter2", "not a Vault"), ("vault:v1:AAAA", "400")];
+        for (configured, message_start) in cases {
+            match provider
+                .resolve("hmac", &SecretString::from(configured))
+                .await
+            {
+                Err(SecretProviderError::Vault { name, message }) => {
+                    assert_eq!(name, "hmac");
+                    assert!(message.starts_with(message_start), "{}", message);
+                }
+                _ => panic!("Expected a Vault error"),
+            }
+        }
+
+        for configuration in [
+            configuration(stand_in.address(), "wrong-token", "app"),
+            configuration(stand_in.address(), "test-token", "other"),
+        ] {
+            let provider = VaultTransitSecretProvider::new(&configuration).unwrap();
+            assert!(provider.resolve("hmac", &wrapped).await.is_err());
+        }
+
+        stand_in.stop().await;
+    }
+}
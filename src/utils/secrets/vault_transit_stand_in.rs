@@ -0,0 +1,185 @@
+use crate::utils::{AesGcmCipher, SecurityError};
+use actix_web::dev::{Server, ServerHandle};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, HttpServer};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::json;
+use std::net::TcpListener;
+use subtle::ConstantTimeEq;
+
+const VAULT_CIPHERTEXT_PREFIX: &str = "vault:v1:";
+
+/// A local stand-in for the transit secrets engine of HashiCorp Vault, so that the
+/// `vault_transit` secret provider runs without a Vault, e.g. in tests.
+///
+/// Serves `POST /v1/<mount>/encrypt/<key name>` and `POST /v1/<mount>/decrypt/<key name>`
+/// for a single AES-256-GCM key, authenticated with `X-Vault-Token`. Key versions, batches
+/// and every other endpoint are left out.
+pub struct VaultTransitStandIn {
+    address: String,
+    state: web::Data<StandInState>,
+    server: ServerHandle,
+}
+
+struct StandInState {
+    mount: String,
+    key_name: String,
+    token: SecretString,
+    cipher: AesGcmCipher,
+}
+
+#[derive(Deserialize)]
+struct EncryptRequest {
+    plaintext: SecretString,
+}
+
+#[derive(Deserialize)]
+struct DecryptRequest {
+    ciphertext: String,
+}
+
+impl VaultTransitStandIn {
+    /// The returned server has to be awaited or spawned.
+    pub fn start(
+        listener: TcpListener,
+        mount: &str,
+        key_name: &str,
+        token: SecretString,
+        key_hex: SecretString,
+    ) -> Result<(Self, Server), std::io::Error> {
+        let address = format!("http://{}", listener.local_addr()?);
+        let cipher = AesGcmCipher::from_hex(&key_hex)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let state = web::Data::new(StandInState {
+            mount: mount.to_string(),
+            key_name: key_name.to_string(),
+            token,
+            cipher,
+        });
+        let server_state = state.clone();
+        let server = HttpServer::new(move || {
+            actix_web::App::new()
+                .app_data(server_state.clone())
+                .route("/v1/{mount}/encrypt/{key_name}", web::post().to(encrypt))
+                .route("/v1/{mount}/decrypt/{key_name}", web::post().to(decrypt))
+        })
+        .workers(1)
+        .listen(listener)?
+        .run();
+
+        Ok((
+            VaultTransitStandIn {
+                address,
+                state,
+                server: server.handle(),
+            },
+            server,
+        ))
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// What `POST /v1/<mount>/encrypt/<key name>` returns for `plaintext`.
+    pub fn encrypt(&self, plaintext: &SecretString) -> Result<String, SecurityError> {
+        self.state.encrypt(&SecretString::from(
+            BASE64_STANDARD.encode(plaintext.expose_secret()),
+        ))
+    }
+
+    pub async fn stop(self) {
+        self.server.stop(true).await;
+    }
+}
+
+impl StandInState {
+    // Vault plaintexts are base64 encoded bytes, they are encrypted as they are sent
+    fn encrypt(&self, plaintext: &SecretString) -> Result<String, SecurityError> {
+        let encrypted = self.cipher.encrypt(plaintext, &[])?;
+        let encrypted = hex::decode(encrypted).map_err(|_| SecurityError::Encryption)?;
+
+        Ok(format!(
+            "{}{}",
+            VAULT_CIPHERTEXT_PREFIX,
+            BASE64_STANDARD.encode(encrypted)
+        ))
+    }
+
+    fn decrypt(&self, ciphertext: &str) -> Result<SecretString, &'static str> {
+        let encrypted = ciphertext
+            .strip_prefix(VAULT_CIPHERTEXT_PREFIX)
+            .and_then(|encrypted| BASE64_STANDARD.decode(encrypted).ok())
+            .ok_or("invalid ciphertext")?;
+
+        self.cipher
+            .decrypt(&hex::encode(encrypted), &[])
+            .map_err(|_| "cipher: message authentication failed")
+    }
+
+    fn authorize(&self, req: &HttpRequest, path: &(String, String)) -> Result<(), HttpResponse> {
+        let token = req
+            .headers()
+            .get("X-Vault-Token")
+            .map(|token| token.as_bytes())
+            .unwrap_or_default();
+
+        if !bool::from(token.ct_eq(self.token.expose_secret().as_bytes())) {
+            return Err(error_response(StatusCode::FORBIDDEN, "permission denied"));
+        }
+        if path.0 != self.mount {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                "no handler for route",
+            ));
+        }
+        if path.1 != self.key_name {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "encryption key not found",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+async fn encrypt(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: web::Json<EncryptRequest>,
+    state: web::Data<StandInState>,
+) -> HttpResponse {
+    if let Err(response) = state.authorize(&req, &path) {
+        return response;
+    }
+
+    match state.encrypt(&body.plaintext) {
+        Ok(ciphertext) => HttpResponse::Ok().json(json!({ "data": { "ciphertext": ciphertext } })),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+async fn decrypt(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: web::Json<DecryptRequest>,
+    state: web::Data<StandInState>,
+) -> HttpResponse {
+    if let Err(response) = state.authorize(&req, &path) {
+        return response;
+    }
+
+    match state.decrypt(&body.ciphertext) {
+        Ok(plaintext) => {
+            HttpResponse::Ok().json(json!({ "data": { "plaintext": plaintext.expose_secret() } }))
+        }
+        Err(message) => error_response(StatusCode::BAD_REQUEST, message),
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(json!({ "errors": [message] }))
+}
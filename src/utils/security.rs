@@ -1,95 +1,746 @@
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
 use hex;
+use rand::distr::Alphanumeric;
 use rand::Rng;
+use secrecy::{ExposeSecret, SecretString};
+use std::fmt;
+use thiserror::Error;
+use zeroize::Zeroizing;
 
-fn generate_nonce() -> [u8; 12] {
-    let mut nonce = [0u8; 12];
+const GENERATED_PASSWORD_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+/// Errors of the helpers below. None of them carries key material, plaintexts or the input
+/// that failed to decode.
+#[derive(Debug, Error)]
+pub enum SecurityError {
+    #[error("Invalid AES-256 key, expected 64 hex characters")]
+    InvalidKey,
+    #[error("Encryption failed")]
+    Encryption,
+    #[error("Decryption failed")]
+    Decryption,
+    #[error("Malformed ciphertext: {0}")]
+    MalformedCiphertext(String),
+    #[error("Argon2 failed: {0}")]
+    Argon2(String),
+}
+
+fn generate_nonce() -> [u8; NONCE_LENGTH] {
+    let mut nonce = [0u8; NONCE_LENGTH];
     rand::rng().fill(&mut nonce);
     nonce
 }
 
-fn decode_aes_hex_key(key_hex: &str) -> Result<Vec<u8>, String> {
-    match hex::decode(key_hex) {
-        Ok(key) => {
-            println!("Decoded AES hex key: {:?}", key);
-            Ok(key)
-        }
-        Err(e) => {
-            let message = format!("Failed to decode AES hex key: {:?}", e);
-            eprintln!("{}", message);
-            Err(message)
+/// Random alphanumeric password (~190 bits of entropy) for a new tenant role.
+pub fn generate_password() -> SecretString {
+    let password: String = rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(GENERATED_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect();
+
+    SecretString::from(password)
+}
+
+/// Argon2id digest of `value`, keyed with `secret` and salted with `salt`, hex encoded.
+///
+/// The salt is the static `argon2_salt`, equal values give equal digests, while the
+/// `argon2_key` secret keeps digests from being brute forced without the configuration.
+pub fn argon2_digest(
+    value: &SecretString,
+    salt: &SecretString,
+    secret: &SecretString,
+    params: Params,
+) -> Result<String, SecurityError> {
+    let argon2 = Argon2::new_with_secret(
+        secret.expose_secret().as_bytes(),
+        Algorithm::Argon2id,
+        Version::V0x13,
+        params,
+    )
+    .map_err(|e| SecurityError::Argon2(format!("Invalid Argon2 secret: {}", e)))?;
+
+    let mut digest = [0u8; 32];
+    argon2
+        .hash_password_into(
+            value.expose_secret().as_bytes(),
+            salt.expose_secret().as_bytes(),
+            &mut digest,
+        )
+        .map_err(|e| SecurityError::Argon2(e.to_string()))?;
+
+    Ok(hex::encode(digest))
+}
+
+fn argon2_with_pepper(pepper: &SecretString) -> Result<Argon2<'_>, SecurityError> {
+    Argon2::new_with_secret(
+        pepper.expose_secret().as_bytes(),
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .map_err(|e| SecurityError::Argon2(format!("Invalid Argon2 secret: {}", e)))
+}
+
+/// Argon2id hash of a user password in the PHC string format, with a random salt and keyed
+/// with `pepper`.
+///
+/// The pepper is not part of the stored hash, a leaked `users` table alone can't be brute forced.
+pub fn hash_password(
+    password: &SecretString,
+    pepper: &SecretString,
+) -> Result<String, SecurityError> {
+    let mut salt = [0u8; 16];
+    rand::rng().fill(&mut salt);
+    let salt = SaltString::encode_b64(&salt).map_err(|e| SecurityError::Argon2(e.to_string()))?;
+
+    argon2_with_pepper(pepper)?
+        .hash_password(password.expose_secret().as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| SecurityError::Argon2(e.to_string()))
+}
+
+/// Checks a password against a hash from [`hash_password`], the comparison is constant time.
+pub fn verify_password(
+    password: &SecretString,
+    password_hash: &str,
+    pepper: &SecretString,
+) -> Result<bool, SecurityError> {
+    let password_hash = PasswordHash::new(password_hash)
+        .map_err(|e| SecurityError::Argon2(format!("Invalid password hash: {}", e)))?;
+
+    match argon2_with_pepper(pepper)?
+        .verify_password(password.expose_secret().as_bytes(), &password_hash)
+    {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(SecurityError::Argon2(e.to_string())),
+    }
+}
+
+/// An AES-256-GCM key, decoded once when secrets are resolved instead of on every use.
+///
+/// The decoded key only lives in the cipher's key schedule, which is zeroized when the last
+/// clone is dropped. `Debug` never shows it.
+#[derive(Clone)]
+pub struct AesGcmCipher(Aes256Gcm);
+
+impl fmt::Debug for AesGcmCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AesGcmCipher([REDACTED])")
+    }
+}
+
+impl AesGcmCipher {
+    /// From a hex encoded 256 bit key, e.g. `openssl rand -hex 32`.
+    pub fn from_hex(key_hex: &SecretString) -> Result<Self, SecurityError> {
+        let key = Zeroizing::new(
+            hex::decode(key_hex.expose_secret()).map_err(|_| SecurityError::InvalidKey)?,
+        );
+
+        Aes256Gcm::new_from_slice(&key)
+            .map(AesGcmCipher)
+            .map_err(|_| SecurityError::InvalidKey)
+    }
+
+    /// Hex encoded `nonce || ciphertext` of `plaintext`, authenticating `aad` along with it.
+    /// The associated data is not part of the ciphertext, decrypting takes the very same bytes.
+    pub fn encrypt(&self, plaintext: &SecretString, aad: &[u8]) -> Result<String, SecurityError> {
+        let nonce = generate_nonce();
+        let ciphertext = self
+            .0
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.expose_secret().as_bytes(),
+                    aad,
+                },
+            )
+            .map_err(|_| SecurityError::Encryption)?;
+
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend_from_slice(&ciphertext);
+
+        Ok(hex::encode(encrypted))
+    }
+
+    pub fn decrypt(&self, encrypted: &str, aad: &[u8]) -> Result<SecretString, SecurityError> {
+        let encrypted = hex::decode(encrypted)
+            .map_err(|_| SecurityError::MalformedCiphertext("not hex encoded".to_string()))?;
+
+        if encrypted.len() < NONCE_LENGTH {
+            return Err(SecurityError::MalformedCiphertext("too short".to_string()));
         }
+
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+        let plaintext = Zeroizing::new(
+            self.0
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad,
+                    },
+                )
+                .map_err(|_| SecurityError::Decryption)?,
+        );
+
+        std::str::from_utf8(&plaintext)
+            .map(SecretString::from)
+            .map_err(|_| SecurityError::Decryption)
     }
 }
 
-pub fn encrypt_aes_gcm(
-    key_hex: &str,
-    plaintext: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let decoded_aes_hex_key = decode_aes_hex_key(key_hex)?;
-    let cipher = Aes256Gcm::new_from_slice(&decoded_aes_hex_key)
-        .map_err(|e| format!("Failed to create cipher: {:?}", e))?;
+const AES256_GCM_ALGORITHM: &str = "aes256gcm";
 
-    let nonce = generate_nonce();
-    let ciphertext = cipher
-        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
-        .map_err(|e| format!("Encryption failed: {:?}", e))?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiphertextVersion {
+    /// Bare hex, written before ciphertexts named their key.
+    Unversioned,
+    /// Names its key.
+    V1,
+    /// Names its key and is bound to associated data, see [`AesGcmCipher::encrypt`].
+    V2,
+    /// Also names what it is bound to, so that a ciphertext used for something else is told
+    /// apart from one failing to decrypt.
+    V3,
+}
 
-    let mut encrypted = nonce.to_vec();
-    encrypted.extend_from_slice(&ciphertext);
+impl CiphertextVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CiphertextVersion::Unversioned => "",
+            CiphertextVersion::V1 => "v1",
+            CiphertextVersion::V2 => "v2",
+            CiphertextVersion::V3 => "v3",
+        }
+    }
+}
 
-    Ok(hex::encode(encrypted))
+/// A ciphertext naming the key and algorithm it was encrypted with, formatted as
+/// `<version>:aes256gcm:<key id>:<hex encoded nonce || ciphertext>`, and as
+/// `v3:aes256gcm:<key id>:<bound to>:<hex encoded nonce || ciphertext>` from `v3` on.
+///
+/// Ciphertexts written before this format are bare hex and name no key.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VersionedCiphertext<'a> {
+    pub version: CiphertextVersion,
+    pub key_id: Option<&'a str>,
+    pub bound_to: Option<&'a str>,
+    pub payload: &'a str,
 }
 
-pub fn decrypt_aes_gcm(
-    key_hex: &str,
-    encrypted_data: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let decoded_aes_hex_key = decode_aes_hex_key(key_hex)?;
-    let encrypted_bytes = hex::decode(encrypted_data)?;
+impl<'a> VersionedCiphertext<'a> {
+    pub fn parse(value: &'a str) -> Result<Self, SecurityError> {
+        let Some((version, fields)) = value.split_once(':') else {
+            return Ok(VersionedCiphertext {
+                version: CiphertextVersion::Unversioned,
+                key_id: None,
+                bound_to: None,
+                payload: value,
+            });
+        };
+
+        let version = match version {
+            "v1" => CiphertextVersion::V1,
+            "v2" => CiphertextVersion::V2,
+            "v3" => CiphertextVersion::V3,
+            version => {
+                return Err(SecurityError::MalformedCiphertext(format!(
+                    "unsupported version {}",
+                    version
+                )))
+            }
+        };
+        let names_binding = version == CiphertextVersion::V3;
+        let fields = fields
+            .splitn(if names_binding { 4 } else { 3 }, ':')
+            .collect::<Vec<_>>();
 
-    if encrypted_bytes.len() < 12 {
-        return Err("Encrypted data is too short".into());
+        match fields[..] {
+            [AES256_GCM_ALGORITHM, key_id, bound_to, payload]
+                if !key_id.is_empty() && !bound_to.is_empty() =>
+            {
+                Ok(VersionedCiphertext {
+                    version,
+                    key_id: Some(key_id),
+                    bound_to: Some(bound_to),
+                    payload,
+                })
+            }
+            [AES256_GCM_ALGORITHM, key_id, payload] if !names_binding && !key_id.is_empty() => {
+                Ok(VersionedCiphertext {
+                    version,
+                    key_id: Some(key_id),
+                    bound_to: None,
+                    payload,
+                })
+            }
+            [algorithm, ..] if algorithm != AES256_GCM_ALGORITHM => Err(
+                SecurityError::MalformedCiphertext(format!("unsupported algorithm {}", algorithm)),
+            ),
+            _ => Err(SecurityError::MalformedCiphertext(
+                "expected <version>:<algorithm>:<key id>:[<bound to>:]<payload>".to_string(),
+            )),
+        }
     }
 
-    let (nonce, ciphertext) = encrypted_bytes.split_at(12);
+    /// Whether the ciphertext was encrypted with associated data.
+    pub fn is_bound(&self) -> bool {
+        matches!(self.version, CiphertextVersion::V2 | CiphertextVersion::V3)
+    }
 
-    let cipher = Aes256Gcm::new_from_slice(&decoded_aes_hex_key)
-        .map_err(|e| format!("Failed to create cipher: {:?}", e))?;
+    /// The prefix of every ciphertext [`encrypt_versioned_aes_gcm`] writes with `key_id`.
+    pub fn prefix(key_id: &str) -> String {
+        format!(
+            "{}:{}:{}:",
+            CiphertextVersion::V3.as_str(),
+            AES256_GCM_ALGORITHM,
+            key_id
+        )
+    }
+}
+
+/// [`AesGcmCipher::encrypt`] in the [`VersionedCiphertext`] format, naming `bound_to` as what
+/// `aad` binds the ciphertext to.
+pub fn encrypt_versioned_aes_gcm(
+    key_id: &str,
+    cipher: &AesGcmCipher,
+    plaintext: &SecretString,
+    bound_to: &str,
+    aad: &[u8],
+) -> Result<String, SecurityError> {
+    if bound_to.is_empty() || bound_to.contains(':') {
+        return Err(SecurityError::MalformedCiphertext(
+            "what a ciphertext is bound to can't be empty or contain ':'".to_string(),
+        ));
+    }
 
-    let plaintext = cipher
-        .decrypt(Nonce::from_slice(nonce), ciphertext)
-        .map_err(|e| format!("Decryption failed: {:?}", e))?;
+    let encrypted = cipher.encrypt(plaintext, aad)?;
 
-    String::from_utf8(plaintext).map_err(|e| e.into())
+    Ok(format!(
+        "{}{}:{}",
+        VersionedCiphertext::prefix(key_id),
+        bound_to,
+        encrypted
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::configurations::{get_configuration, Configuration, Secrets};
+    use crate::models::TENANT_BASE_ROLE;
+    use crate::telemetry::get_subscriber;
+    use crate::utils::{
+        create_tenant, decrypt_tenant_password, encrypt_tenant_password,
+        fetch_tenant_db_credentials, resolve_secrets, FileSecretProvider, SecretProvider,
+        SecretRefresh,
+    };
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use uuid::Uuid;
 
     const AES_KEY_HEX: &str = "4b5d623f8a9b2dc3e78f5c6a1d3b9f0e2a1c4b7d5e8f0a3c6b9d2e5f8a1c4d7b";
     const ORIGINAL_MESSAGE: &str = "secure_password_test_1";
     const ENCRYPTED_ORIGINAL_MESSAGE: &str = "3d0353bd1f90f4e2d6b001d0c5a9cc23fd65a4712f1d8f9452750d46fe36aaeec120ac11889138bb156e731194eca0d9ff59";
 
+    fn secret(value: &str) -> SecretString {
+        SecretString::from(value)
+    }
+
+    fn cipher() -> AesGcmCipher {
+        AesGcmCipher::from_hex(&secret(AES_KEY_HEX)).unwrap()
+    }
+
     #[test]
     fn test_encrypt_aes_gcm() {
-        let encrypted = encrypt_aes_gcm(AES_KEY_HEX, ORIGINAL_MESSAGE).unwrap();
-
-        println!("Encrypted: {}", encrypted);
+        let encrypted = cipher().encrypt(&secret(ORIGINAL_MESSAGE), &[]).unwrap();
 
         // Verify that the encrypted result is a valid hex string and has the correct length
         assert!(hex::decode(&encrypted).is_ok());
         assert!(encrypted.len() > 24); // At least 12 bytes for nonce + some ciphertext
     }
 
+    #[test]
+    fn test_aes_gcm_cipher() {
+        for key_hex in [
+            "",
+            "4b5d",
+            &AES_KEY_HEX.replace('4', "x"),
+            &AES_KEY_HEX[..62],
+        ] {
+            assert!(matches!(
+                AesGcmCipher::from_hex(&secret(key_hex)),
+                Err(SecurityError::InvalidKey)
+            ));
+        }
+        assert_eq!(format!("{:?}", cipher()), "AesGcmCipher([REDACTED])");
+
+        // The key schedule is wiped once the cipher is dropped
+        fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>() {}
+        assert_zeroize_on_drop::<aes::Aes256>();
+    }
+
+    #[test]
+    fn test_generate_password() {
+        let password = generate_password();
+        let other_password = generate_password();
+
+        assert_eq!(password.expose_secret().len(), GENERATED_PASSWORD_LENGTH);
+        assert!(password
+            .expose_secret()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(password.expose_secret(), other_password.expose_secret());
+    }
+
+    #[test]
+    fn test_argon2_digest() {
+        let salt = secret("MyFixedStaticSaltValue");
+        let digest = |value: &str, salt: &SecretString, key: &str| {
+            argon2_digest(&secret(value), salt, &secret(key), Params::default())
+        };
+
+        assert_eq!(
+            digest("value", &salt, "secret").unwrap(),
+            digest("value", &salt, "secret").unwrap()
+        );
+        assert_ne!(
+            digest("value", &salt, "secret").unwrap(),
+            digest("value", &salt, "other secret").unwrap()
+        );
+        assert_ne!(
+            digest("value", &salt, "secret").unwrap(),
+            digest("other value", &salt, "secret").unwrap()
+        );
+        // Argon2 requires salts of at least 8 bytes
+        assert!(digest("value", &secret("short"), "secret").is_err());
+    }
+
+    #[test]
+    fn test_hash_password() {
+        let pepper = secret("pepper");
+        let password_hash = hash_password(&secret("correct horse"), &pepper).unwrap();
+
+        assert!(password_hash.starts_with("$argon2id$"));
+        assert_ne!(
+            password_hash,
+            hash_password(&secret("correct horse"), &pepper).unwrap()
+        );
+        assert!(verify_password(&secret("correct horse"), &password_hash, &pepper).unwrap());
+        assert!(!verify_password(&secret("wrong horse"), &password_hash, &pepper).unwrap());
+        assert!(!verify_password(
+            &secret("correct horse"),
+            &password_hash,
+            &secret("other pepper")
+        )
+        .unwrap());
+        assert!(verify_password(&secret("correct horse"), "not a hash", &pepper).is_err());
+    }
+
+    #[test]
+    fn test_aes_gcm_with_aad() {
+        let cipher = cipher();
+        let encrypted = cipher
+            .encrypt(&secret(ORIGINAL_MESSAGE), b"tenant a")
+            .unwrap();
+
+        assert_eq!(
+            cipher
+                .decrypt(&encrypted, b"tenant a")
+                .unwrap()
+                .expose_secret(),
+            ORIGINAL_MESSAGE
+        );
+        assert!(matches!(
+            cipher.decrypt(&encrypted, b"tenant b"),
+            Err(SecurityError::Decryption)
+        ));
+        assert!(cipher.decrypt(&encrypted, &[]).is_err());
+        assert!(matches!(
+            cipher.decrypt("3d03", &[]),
+            Err(SecurityError::MalformedCiphertext(_))
+        ));
+    }
+
+    #[test]
+    fn test_versioned_ciphertext() {
+        let cipher = cipher();
+        let encrypted = encrypt_versioned_aes_gcm(
+            "2026-10",
+            &cipher,
+            &secret(ORIGINAL_MESSAGE),
+            "tenant-a",
+            b"tenant a",
+        )
+        .unwrap();
+        let ciphertext = VersionedCiphertext::parse(&encrypted).unwrap();
+
+        assert!(encrypted.starts_with("v3:aes256gcm:2026-10:tenant-a:"));
+        assert_eq!(ciphertext.key_id, Some("2026-10"));
+        assert_eq!(ciphertext.bound_to, Some("tenant-a"));
+        assert!(ciphertext.is_bound());
+        assert_eq!(
+            cipher
+                .decrypt(ciphertext.payload, b"tenant a")
+                .unwrap()
+                .expose_secret(),
+            ORIGINAL_MESSAGE
+        );
+        for bound_to in ["", "tenant:a"] {
+            assert!(encrypt_versioned_aes_gcm(
+                "2026-10",
+                &cipher,
+                &secret(ORIGINAL_MESSAGE),
+                bound_to,
+                b"tenant a"
+            )
+            .is_err());
+        }
+
+        let v2 = format!("v2:aes256gcm:2026-10:{}", ENCRYPTED_ORIGINAL_MESSAGE);
+        assert_eq!(
+            VersionedCiphertext::parse(&v2).unwrap(),
+            VersionedCiphertext {
+                version: CiphertextVersion::V2,
+                key_id: Some("2026-10"),
+                bound_to: None,
+                payload: ENCRYPTED_ORIGINAL_MESSAGE
+            }
+        );
+        let v1 = format!("v1:aes256gcm:2026-10:{}", ENCRYPTED_ORIGINAL_MESSAGE);
+        assert_eq!(
+            VersionedCiphertext::parse(&v1).unwrap(),
+            VersionedCiphertext {
+                version: CiphertextVersion::V1,
+                key_id: Some("2026-10"),
+                bound_to: None,
+                payload: ENCRYPTED_ORIGINAL_MESSAGE
+            }
+        );
+        assert_eq!(
+            VersionedCiphertext::parse(ENCRYPTED_ORIGINAL_MESSAGE).unwrap(),
+            VersionedCiphertext {
+                version: CiphertextVersion::Unversioned,
+                key_id: None,
+                bound_to: None,
+                payload: ENCRYPTED_ORIGINAL_MESSAGE
+            }
+        );
+        assert!(VersionedCiphertext::parse("v4:aes256gcm:2026-10:00").is_err());
+        assert!(VersionedCiphertext::parse("v2:chacha20:2026-10:00").is_err());
+        assert!(VersionedCiphertext::parse("v2:aes256gcm::00").is_err());
+        assert!(VersionedCiphertext::parse("v2:aes256gcm").is_err());
+        assert!(VersionedCiphertext::parse("v3:aes256gcm:2026-10:00").is_err());
+        assert!(VersionedCiphertext::parse("v3:aes256gcm:2026-10::00").is_err());
+    }
+
     #[test]
     fn test_decrypt_aes_gcm() {
-        let decrypted = decrypt_aes_gcm(AES_KEY_HEX, ENCRYPTED_ORIGINAL_MESSAGE).unwrap();
+        let decrypted = cipher().decrypt(ENCRYPTED_ORIGINAL_MESSAGE, &[]).unwrap();
+
+        assert_eq!(decrypted.expose_secret(), ORIGINAL_MESSAGE);
+    }
+
+    const LEAK_CHECK_KEY_HEX: &str =
+        "5ec7e75ec7e75ec7e75ec7e75ec7e75ec7e75ec7e75ec7e75ec7e75ec7e75ec7";
+    const LEAK_CHECK_PLAINTEXT: &str = "plaintext-that-never-gets-printed";
+    const LEAK_CHECK_PEPPER: &str = "pepper-that-never-gets-printed";
+
+    /// The development configuration with the leak check key as its only, active, keyring key.
+    fn leak_check_configuration() -> Configuration {
+        let mut configuration = get_configuration().expect("Failed to read configuration.");
+        configuration.secrets.keyring.active_key_id = "leak-check".to_string();
+        configuration.secrets.keyring.keys =
+            [("leak-check".to_string(), secret(LEAK_CHECK_KEY_HEX))].into();
+        configuration
+            .secrets
+            .store
+            .replace(Secrets::try_from(&configuration.secrets).unwrap());
+
+        configuration
+    }
+
+    /// Logs everything down to `trace` to stdout, where the parent test reads it.
+    fn trace_to_stdout() -> tracing::subscriber::DefaultGuard {
+        tracing::subscriber::set_default(get_subscriber("leak-check", true, std::io::stdout))
+    }
+
+    // Run on its own by `test_no_secrets_written_to_stdout_or_stderr`, assertions don't print
+    // their operands as a failure would print the secrets itself
+    #[actix_web::test]
+    #[ignore = "run by test_no_secrets_written_to_stdout_or_stderr"]
+    async fn exercise_security_helpers() {
+        let _tracing = trace_to_stdout();
+        let key = secret(LEAK_CHECK_KEY_HEX);
+        let plaintext = secret(LEAK_CHECK_PLAINTEXT);
+        let pepper = secret(LEAK_CHECK_PEPPER);
+        let cipher = AesGcmCipher::from_hex(&key).unwrap();
+
+        let encrypted = cipher.encrypt(&plaintext, b"aad").unwrap();
+        assert!(cipher.decrypt(&encrypted, b"aad").is_ok());
+        assert!(cipher.decrypt(&encrypted, b"other aad").is_err());
+        let encrypted =
+            encrypt_versioned_aes_gcm("leak-check", &cipher, &plaintext, "leak-check", &[])
+                .unwrap();
+        assert!(VersionedCiphertext::parse(&encrypted).is_ok());
+
+        // Errors neither print nor carry what failed to decode
+        let invalid_key = secret(&LEAK_CHECK_KEY_HEX.replace('e', "g"));
+        let error = AesGcmCipher::from_hex(&invalid_key).unwrap_err();
+        assert!(!format!("{} {:?}", error, error).contains("5ec7"));
+        assert!(!format!("{:?}", cipher).contains("5ec7"));
+
+        let password_hash = hash_password(&plaintext, &pepper).unwrap();
+        assert!(verify_password(&plaintext, &password_hash, &pepper).unwrap());
+        assert!(argon2_digest(
+            &plaintext,
+            &secret("MyFixedStaticSaltValue"),
+            &pepper,
+            Params::default()
+        )
+        .is_ok());
 
-        assert_eq!(decrypted, ORIGINAL_MESSAGE);
+        // Tenant role passwords, decrypted for the right tenant only
+        let configuration = leak_check_configuration();
+        let tenant_id = Uuid::new_v4();
+        let db_user = "tenant_base_leak_check";
+        let encrypted =
+            encrypt_tenant_password(&plaintext, &tenant_id, db_user, &configuration).unwrap();
+        assert!(decrypt_tenant_password(&encrypted, &tenant_id, db_user, &configuration).is_ok());
+        let error = decrypt_tenant_password(&encrypted, &Uuid::new_v4(), db_user, &configuration)
+            .unwrap_err();
+        assert!(!format!("{} {:?}", error, error).contains(LEAK_CHECK_PLAINTEXT));
+
+        // Secrets resolved from files, then refreshed in the background
+        let directory = std::env::temp_dir().join(format!("leak-check-{}", Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        std::fs::write(directory.join("keyring.leak-check"), LEAK_CHECK_KEY_HEX).unwrap();
+        std::fs::write(directory.join("argon2_key"), LEAK_CHECK_PEPPER).unwrap();
+        let provider: Arc<dyn SecretProvider> = Arc::new(FileSecretProvider::new(&directory));
+        assert!(resolve_secrets(provider.as_ref(), &configuration.secrets)
+            .await
+            .is_ok());
+
+        let secret_refresh = SecretRefresh::start(
+            provider,
+            configuration.secrets.clone(),
+            Duration::from_millis(20),
+        );
+        std::fs::write(directory.join("hmac"), LEAK_CHECK_PLAINTEXT).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        secret_refresh.shutdown().await;
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    // Run on its own by `test_no_tenant_credentials_written_to_stdout_or_stderr`
+    #[actix_web::test]
+    #[ignore = "run by test_no_tenant_credentials_written_to_stdout_or_stderr"]
+    async fn exercise_tenant_credentials() {
+        let _tracing = trace_to_stdout();
+        let configuration = leak_check_configuration();
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let name = format!("leak_check_{}", Uuid::new_v4().simple());
+        let db_user = format!("{}_{}", TENANT_BASE_ROLE, name);
+        let tenant_id = create_tenant(&name, &pool, &configuration).await.unwrap();
+
+        // Only decrypted here, the role keeps the password it was created with
+        let encrypted = encrypt_tenant_password(
+            &secret(LEAK_CHECK_PLAINTEXT),
+            &tenant_id,
+            &db_user,
+            &configuration,
+        )
+        .unwrap();
+        sqlx::query("UPDATE tenants SET db_password_encrypted = $2 WHERE id = $1")
+            .bind(tenant_id)
+            .bind(&encrypted)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let credentials = fetch_tenant_db_credentials(&tenant_id, &pool, &configuration)
+            .await
+            .unwrap();
+        assert!(credentials.db_password.expose_secret() == LEAK_CHECK_PLAINTEXT);
+
+        sqlx::query("DELETE FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(&format!("DROP ROLE {}", db_user))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    /// What the exercise printed, run in a child process as the test harness would otherwise
+    /// capture it.
+    fn printed_by(exercise: &str) -> String {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                &format!("utils::security::tests::{}", exercise),
+                "--exact",
+                "--ignored",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .output()
+            .unwrap();
+        let printed = [output.stdout, output.stderr].concat();
+        let printed = String::from_utf8_lossy(&printed).into_owned();
+
+        // Neither the output nor the secrets are part of the messages, they could hold key material
+        assert!(output.status.success(), "{}", output.status);
+        assert!(printed.contains("test result: ok. 1 passed"));
+
+        printed
+    }
+
+    fn assert_no_secrets_printed(printed: &str) {
+        let key = hex::decode(LEAK_CHECK_KEY_HEX).unwrap();
+        for (i, secret) in [
+            LEAK_CHECK_KEY_HEX.to_string(),
+            LEAK_CHECK_KEY_HEX.to_uppercase(),
+            format!("{:?}", key),
+            format!("{:?}", &key[..4]).trim_end_matches(']').to_string(),
+            LEAK_CHECK_PLAINTEXT.to_string(),
+            LEAK_CHECK_PEPPER.to_string(),
+        ]
+        .iter()
+        .enumerate()
+        {
+            assert!(
+                !printed.contains(secret.as_str()),
+                "Secret {} was printed",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_secrets_written_to_stdout_or_stderr() {
+        let printed = printed_by("exercise_security_helpers");
+
+        assert_no_secrets_printed(&printed);
+        assert!(!printed.contains("5ec7"));
+    }
+
+    // Runs against the development database: `cargo test -- --ignored`
+    #[test]
+    #[ignore = "requires a migrated database"]
+    fn test_no_tenant_credentials_written_to_stdout_or_stderr() {
+        assert_no_secrets_printed(&printed_by("exercise_tenant_credentials"));
     }
 }
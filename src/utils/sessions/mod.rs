@@ -0,0 +1,5 @@
+mod redis_sessions;
+mod session_backend;
+
+pub use redis_sessions::*;
+pub use session_backend::*;
@@ -0,0 +1,196 @@
+use crate::configurations::RedisConfiguration;
+use crate::models::AppError;
+use crate::utils::USER_ID_SESSION_KEY;
+use actix_session::storage::{RedisSessionStore, SessionKey};
+use actix_web::cookie::time::Duration;
+use anyhow::anyhow;
+use deadpool_redis::redis::{self, AsyncCommands};
+use deadpool_redis::{Config, Pool, PoolConfig, Runtime, Timeouts};
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+use uuid::Uuid;
+
+const SESSION_KEY_PREFIX: &str = "session:";
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions:";
+
+/// Sessions held in Redis, with an index of each logged in user's sessions so they can be revoked.
+///
+/// Session state is stored by actix's [`RedisSessionStore`] under `session:<key>`, the index is a
+/// set of session keys under `user_sessions:<tenant id>:<user id>` expiring with its newest session.
+#[derive(Clone)]
+pub struct RedisSessions {
+    store: RedisSessionStore,
+    pool: Pool,
+    tenant_session_key: String,
+}
+
+impl RedisSessions {
+    /// Connections are pooled, up to `pool_max_size` of them, waiting at most
+    /// `pool_timeout_in_seconds` for one. Nothing connects until the first session is stored.
+    pub async fn from_configuration(
+        configuration: &RedisConfiguration,
+        tenant_session_key: &str,
+    ) -> Result<Self, String> {
+        let timeout = Some(StdDuration::from_secs(
+            configuration.pool_timeout_in_seconds,
+        ));
+        let mut config = Config::from_url(configuration.redis_url());
+        config.pool = Some(PoolConfig {
+            max_size: configuration.pool_max_size,
+            timeouts: Timeouts {
+                wait: timeout,
+                create: timeout,
+                recycle: timeout,
+            },
+            ..PoolConfig::default()
+        });
+
+        let pool = config
+            .create_pool(Some(Runtime::Tokio1))
+            .map_err(|e| format!("Invalid Redis configuration: {}", e))?;
+        let store = RedisSessionStore::builder_pooled(pool.clone())
+            .cache_keygen(|session_key| format!("{}{}", SESSION_KEY_PREFIX, session_key))
+            .build()
+            .await
+            .map_err(|e| format!("Failed to create the Redis session store: {}", e))?;
+
+        Ok(RedisSessions {
+            store,
+            pool,
+            tenant_session_key: tenant_session_key.to_string(),
+        })
+    }
+
+    pub fn store(&self) -> &RedisSessionStore {
+        &self.store
+    }
+
+    /// Adds the session to the index of its user, sessions without a logged in user are skipped.
+    pub(crate) async fn index(
+        &self,
+        session_key: &SessionKey,
+        session_state: &HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        let Some((tenant_id, user_id)) = logged_in_user(session_state, &self.tenant_session_key)
+        else {
+            return Ok(());
+        };
+
+        let user_sessions_key = user_sessions_key(&tenant_id, &user_id);
+        let mut connection = self.pool.get().await?;
+        redis::pipe()
+            .atomic()
+            .sadd(&user_sessions_key, session_key.as_ref())
+            .ignore()
+            .expire(&user_sessions_key, ttl.whole_seconds())
+            .ignore()
+            .query_async::<()>(&mut connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes every session of the user, returning how many were still alive.
+    #[tracing::instrument(
+        name = "Revoking user sessions.",
+        fields(tenant_id = %tenant_id, user_id = %user_id),
+        skip(self)
+    )]
+    pub async fn revoke_user_sessions(
+        &self,
+        tenant_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<usize, AppError> {
+        let user_sessions_key = user_sessions_key(tenant_id, user_id);
+        let mut connection = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+
+        let session_keys: Vec<String> = connection
+            .smembers(&user_sessions_key)
+            .await
+            .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+        let mut keys: Vec<String> = session_keys
+            .iter()
+            .map(|session_key| format!("{}{}", SESSION_KEY_PREFIX, session_key))
+            .collect();
+        keys.push(user_sessions_key);
+
+        // The index itself is always deleted, only the sessions are counted
+        let deleted: usize = connection
+            .del(&keys)
+            .await
+            .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+
+        Ok(deleted.saturating_sub(1))
+    }
+}
+
+fn user_sessions_key(tenant_id: &Uuid, user_id: &Uuid) -> String {
+    format!("{}{}:{}", USER_SESSIONS_KEY_PREFIX, tenant_id, user_id)
+}
+
+/// The tenant and user stored by [`crate::utils::UserSession::start`], values are JSON encoded.
+fn logged_in_user(
+    session_state: &HashMap<String, String>,
+    tenant_session_key: &str,
+) -> Option<(Uuid, Uuid)> {
+    let read = |key: &str| {
+        session_state
+            .get(key)
+            .and_then(|value| serde_json::from_str::<Uuid>(value).ok())
+    };
+
+    Some((read(tenant_session_key)?, read(USER_ID_SESSION_KEY)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logged_in_user() {
+        let tenant_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let state = |entries: &[(&str, String)]| -> HashMap<String, String> {
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect()
+        };
+        let json = |id: &Uuid| serde_json::to_string(id).unwrap();
+
+        assert_eq!(
+            logged_in_user(
+                &state(&[("tenant_id", json(&tenant_id)), ("user_id", json(&user_id))]),
+                "tenant_id"
+            ),
+            Some((tenant_id, user_id))
+        );
+        // A tenant picked for the session without a login
+        assert_eq!(
+            logged_in_user(&state(&[("tenant_id", json(&tenant_id))]), "tenant_id"),
+            None
+        );
+        assert_eq!(
+            logged_in_user(
+                &state(&[("tenant_id", json(&tenant_id)), ("user_id", json(&user_id))]),
+                "other_key"
+            ),
+            None
+        );
+        assert_eq!(
+            logged_in_user(
+                &state(&[
+                    ("tenant_id", json(&tenant_id)),
+                    ("user_id", "not a uuid".to_string())
+                ]),
+                "tenant_id"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::models::AppError;
+use crate::utils::RedisSessions;
+use actix_session::storage::{
+    CookieSessionStore, LoadError, SaveError, SessionKey, SessionStore, UpdateError,
+};
+use actix_web::cookie::time::Duration;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// The session store picked by `application.session_store`, see [`SessionStoreBackend`].
+///
+/// [`SessionStoreBackend`]: crate::configurations::SessionStoreBackend
+#[derive(Clone)]
+pub enum SessionBackend {
+    Cookie,
+    Redis(Box<RedisSessions>),
+}
+
+impl SessionBackend {
+    /// Logs the user out of all their sessions, see [`RedisSessions::revoke_user_sessions`].
+    ///
+    /// Sessions held in cookies can't be reached, revoking them is a conflict.
+    pub async fn revoke_user_sessions(
+        &self,
+        tenant_id: &Uuid,
+        user_id: &Uuid,
+    ) -> Result<usize, AppError> {
+        match self {
+            SessionBackend::Cookie => Err(AppError::ConflictError(
+                "Sessions held in cookies can't be revoked".to_string(),
+            )),
+            SessionBackend::Redis(redis_sessions) => {
+                redis_sessions
+                    .revoke_user_sessions(tenant_id, user_id)
+                    .await
+            }
+        }
+    }
+}
+
+impl SessionStore for SessionBackend {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<HashMap<String, String>>, LoadError> {
+        match self {
+            SessionBackend::Cookie => CookieSessionStore::default().load(session_key).await,
+            SessionBackend::Redis(redis_sessions) => redis_sessions.store().load(session_key).await,
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        match self {
+            SessionBackend::Cookie => CookieSessionStore::default().save(session_state, ttl).await,
+            SessionBackend::Redis(redis_sessions) => {
+                let session_key = redis_sessions
+                    .store()
+                    .save(session_state.clone(), ttl)
+                    .await?;
+                redis_sessions
+                    .index(&session_key, &session_state, ttl)
+                    .await
+                    .map_err(SaveError::Other)?;
+
+                Ok(session_key)
+            }
+        }
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        match self {
+            SessionBackend::Cookie => {
+                CookieSessionStore::default()
+                    .update(session_key, session_state, ttl)
+                    .await
+            }
+            SessionBackend::Redis(redis_sessions) => {
+                let session_key = redis_sessions
+                    .store()
+                    .update(session_key, session_state.clone(), ttl)
+                    .await?;
+                redis_sessions
+                    .index(&session_key, &session_state, ttl)
+                    .await
+                    .map_err(UpdateError::Other)?;
+
+                Ok(session_key)
+            }
+        }
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            SessionBackend::Cookie => {
+                CookieSessionStore::default()
+                    .update_ttl(session_key, ttl)
+                    .await
+            }
+            SessionBackend::Redis(redis_sessions) => {
+                redis_sessions.store().update_ttl(session_key, ttl).await
+            }
+        }
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        match self {
+            SessionBackend::Cookie => CookieSessionStore::default().delete(session_key).await,
+            SessionBackend::Redis(redis_sessions) => {
+                redis_sessions.store().delete(session_key).await
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::configurations::Configuration;
+use crate::models::{
+    AppError, AppState, SharedPoolTenant, TenantIsolationMode, TenantStatus,
+    TenantTransactionSettings,
+};
+use crate::utils::{get_pool_for_tenant, is_connection_failure, rebuild_tenant_pool};
+use anyhow::anyhow;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Starts a transaction acting as the tenant, whichever pool strategy is configured.
+///
+/// With a shared pool, tenants of the shared database are switched to within the transaction.
+/// Tenants isolated by schema or database always get a pool of their own.
+pub async fn begin_tenant_transaction(
+    tenant_id: &Uuid,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<Transaction<'static, Postgres>, AppError> {
+    begin_tenant_transaction_with(
+        tenant_id,
+        state,
+        pool,
+        configuration,
+        &TenantTransactionSettings::default(),
+    )
+    .await
+}
+
+/// Same as [`begin_tenant_transaction`], with the given isolation level and statement timeout.
+#[tracing::instrument(
+    name = "Starting transaction for tenant.",
+    fields(tenant_id = %tenant_id),
+    skip(state, pool, configuration)
+)]
+pub async fn begin_tenant_transaction_with(
+    tenant_id: &Uuid,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+    settings: &TenantTransactionSettings,
+) -> Result<Transaction<'static, Postgres>, AppError> {
+    let mut transaction = begin(tenant_id, state, pool, configuration, settings).await?;
+
+    if let Some(statement_timeout) = settings.statement_timeout {
+        sqlx::query("SELECT set_config('statement_timeout', $1, true)")
+            .bind(format!("{}ms", statement_timeout.as_millis()))
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    Ok(transaction)
+}
+
+async fn begin(
+    tenant_id: &Uuid,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+    settings: &TenantTransactionSettings,
+) -> Result<Transaction<'static, Postgres>, AppError> {
+    if let Some(shared_pool) = &state.shared_pool {
+        let tenant = match shared_pool.tenant(tenant_id) {
+            Some(tenant) => tenant,
+            None => {
+                let tenant = fetch_shared_pool_tenant(tenant_id, pool).await?;
+                shared_pool.remember(*tenant_id, tenant.clone());
+                tenant
+            }
+        };
+
+        tenant.status.ensure_active()?;
+
+        if tenant.isolation_mode == TenantIsolationMode::Shared {
+            return shared_pool
+                .begin(tenant_id, &tenant, settings)
+                .await
+                .map_err(transaction_error);
+        }
+    }
+
+    let tenant_pool = get_pool_for_tenant(tenant_id, state, pool, configuration).await?;
+
+    let error = match tenant_pool.begin_with(settings.begin_statement()).await {
+        Ok(transaction) => return Ok(transaction),
+        Err(e) if is_connection_failure(&e) => e,
+        Err(e) => return Err(transaction_error(e)),
+    };
+
+    // The cached pool could not connect: rebuild it, unless that happened already, and retry once
+    tracing::warn!(error = %error, "Tenant pool can no longer connect");
+    let tenant_pool = match state.pools.get(tenant_id) {
+        Some(current) if Arc::ptr_eq(&current.pool, &tenant_pool) => {
+            drop(tenant_pool);
+            let rebuilt =
+                rebuild_tenant_pool(tenant_id, &current, state, pool, configuration).await?;
+            Arc::clone(&rebuilt.pool)
+        }
+        _ => get_pool_for_tenant(tenant_id, state, pool, configuration).await?,
+    };
+
+    tenant_pool
+        .begin_with(settings.begin_statement())
+        .await
+        .map_err(transaction_error)
+}
+
+fn transaction_error(e: sqlx::Error) -> AppError {
+    AppError::InternalError(anyhow!("Failed to start tenant transaction: {}", e))
+}
+
+async fn fetch_shared_pool_tenant(
+    tenant_id: &Uuid,
+    pool: &PgPool,
+) -> Result<SharedPoolTenant, AppError> {
+    let (db_user, isolation_mode, status): (String, String, String) =
+        sqlx::query_as("SELECT db_user, isolation_mode, status FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .fetch_one(pool)
+            .await?;
+
+    let isolation_mode = TenantIsolationMode::try_from(isolation_mode)
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+    let status = TenantStatus::try_from(status).map_err(|e| AppError::InternalError(anyhow!(e)))?;
+
+    Ok(SharedPoolTenant {
+        db_user,
+        isolation_mode,
+        status,
+    })
+}
+
+// These run against the development database: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::{get_configuration, TenantPoolStrategy};
+    use crate::models::{SharedTenantPool, TenantPoolRegistry};
+    use sqlx::postgres::PgPoolOptions;
+
+    const SWITCHER_PASSWORD: &str = "tenant_switcher_isolation_test";
+
+    async fn setup(strategy: TenantPoolStrategy) -> (AppState, PgPool, Configuration) {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+
+        let shared_pool = match strategy {
+            TenantPoolStrategy::PerTenant => None,
+            strategy => {
+                sqlx::query(&format!(
+                    "ALTER ROLE tenant_switcher WITH LOGIN PASSWORD '{}'",
+                    SWITCHER_PASSWORD
+                ))
+                .execute(&pool)
+                .await
+                .expect("Failed to enable the switcher login.");
+
+                let connect_options = configuration
+                    .database
+                    .with_db()
+                    .username("tenant_switcher")
+                    .password(SWITCHER_PASSWORD);
+                let switcher_pool = PgPoolOptions::new()
+                    .max_connections(2)
+                    .connect_with(connect_options)
+                    .await
+                    .expect("Failed to connect as the switcher.");
+
+                Some(SharedTenantPool::new(switcher_pool, strategy))
+            }
+        };
+
+        let state = AppState {
+            pools: TenantPoolRegistry::new(),
+            shared_pool,
+        };
+
+        (state, pool, configuration)
+    }
+
+    async fn shared_tenants(pool: &PgPool) -> (Uuid, Uuid) {
+        let tenants: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM tenants WHERE isolation_mode = 'shared' ORDER BY name LIMIT 2",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            tenants.len(),
+            2,
+            "Two tenants in the shared database are needed"
+        );
+
+        (tenants[0], tenants[1])
+    }
+
+    async fn assert_tenants_are_isolated(strategy: TenantPoolStrategy) {
+        let (state, pool, configuration) = setup(strategy).await;
+        let (tenant_a, tenant_b) = shared_tenants(&pool).await;
+
+        // Tenant A creates a user, tagged with its own tenant id
+        let mut transaction = begin_tenant_transaction(&tenant_a, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        let user_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO users (first_name, last_name) VALUES ('Isolation', 'Test') RETURNING id",
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .unwrap();
+        let owner: Uuid = sqlx::query_scalar("SELECT tenant_id FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        assert_eq!(owner, tenant_a);
+
+        // Tenant B can neither see, change nor write on behalf of tenant A
+        let mut transaction = begin_tenant_transaction(&tenant_b, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        let visible: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .unwrap();
+        let updated = sqlx::query("UPDATE users SET first_name = 'Hijacked' WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+            .unwrap();
+        let forged = sqlx::query(
+            "INSERT INTO users (first_name, last_name, tenant_id) VALUES ('Forged', 'Test', $1)",
+        )
+        .bind(tenant_a)
+        .execute(&mut *transaction)
+        .await;
+        transaction.rollback().await.unwrap();
+
+        assert_eq!(visible, 0);
+        assert_eq!(updated.rows_affected(), 0);
+        assert!(forged.is_err());
+
+        // Tenant A still sees its user
+        let mut transaction = begin_tenant_transaction(&tenant_a, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        let deleted = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        assert_eq!(deleted.rows_affected(), 1);
+
+        // Without switching, the shared pool has no access at all
+        if let Some(shared_pool) = &state.shared_pool {
+            let unswitched = sqlx::query("SELECT COUNT(*) FROM users")
+                .execute(&shared_pool.pool)
+                .await;
+
+            assert!(unswitched.is_err());
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_per_tenant_pools_isolate_tenants() {
+        assert_tenants_are_isolated(TenantPoolStrategy::PerTenant).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_shared_set_role_isolates_tenants() {
+        assert_tenants_are_isolated(TenantPoolStrategy::SharedSetRole).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_shared_tenant_setting_isolates_tenants() {
+        assert_tenants_are_isolated(TenantPoolStrategy::SharedTenantSetting).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_shared_tenants_get_no_pool_of_their_own() {
+        let (state, pool, configuration) = setup(TenantPoolStrategy::SharedSetRole).await;
+        let (tenant_a, _) = shared_tenants(&pool).await;
+
+        let own_pool = get_pool_for_tenant(&tenant_a, &state, &pool, &configuration).await;
+        let transaction = begin_tenant_transaction(&tenant_a, &state, &pool, &configuration).await;
+
+        assert!(own_pool.is_err());
+        assert!(transaction.is_ok());
+        assert!(state.pools.get(&tenant_a).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::models::AppState;
+use crate::models::{AppState, EvictionReason};
 use actix_web::web;
 use chrono::Utc;
 use std::time::Duration;
@@ -8,37 +8,32 @@ use std::time::Duration;
     skip(state, idle_duration_in_seconds)
 )]
 pub async fn cleanup_idle_tenant_pools(state: &web::Data<AppState>, idle_duration_in_seconds: u64) {
-    let mut pools = state.pools.lock().await;
     let now = Utc::now();
     let idle_duration = Duration::from_secs(idle_duration_in_seconds);
-    let mut pools_to_retain = Vec::new();
 
-    // Check each pool
-    for (key, tenant_pool) in pools.iter() {
-        let tenant_pool = tenant_pool.lock().await;
-
-        if now
-            .signed_duration_since(tenant_pool.last_accessed)
+    for (tenant_id, tenant_pool) in state.pools.snapshot() {
+        let is_idle = now
+            .signed_duration_since(tenant_pool.last_accessed())
             .num_seconds()
-            < idle_duration.as_secs() as i64
-        {
-            pools_to_retain.push(*key);
+            >= idle_duration.as_secs() as i64;
+
+        // Warm pools stay around regardless of activity
+        if is_idle && !tenant_pool.is_kept_warm() {
+            // Draining and closing happens in the background
+            state
+                .pools
+                .evict(&tenant_id, &tenant_pool, EvictionReason::Idle);
         }
     }
-
-    // Now use retain with a synchronous closure
-    pools.retain(|key, _| pools_to_retain.contains(key));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::TenantPool;
+    use crate::models::{TenantPool, TenantPoolRegistry};
     use chrono::{DateTime, Duration as ChronoDuration, Utc};
     use sqlx::postgres::PgPoolOptions;
     use sqlx::PgPool;
-    use std::{collections::HashMap, sync::Arc};
-    use tokio::sync::Mutex;
     use uuid::Uuid;
 
     // Mock pool that doesn't try to connect to a database
@@ -55,18 +50,17 @@ mod tests {
     }
 
     async fn create_test_state(pools_data: Vec<(Uuid, DateTime<Utc>)>) -> web::Data<AppState> {
-        let mut pools = HashMap::new();
+        let pools = TenantPoolRegistry::new();
 
         for (id, last_accessed) in pools_data {
-            let tenant_pool = Arc::new(Mutex::new(TenantPool {
-                pool: Arc::new(MockPool::into_pg_pool()),
-                last_accessed,
-            }));
+            let tenant_pool =
+                TenantPool::with_last_accessed(MockPool::into_pg_pool(), last_accessed);
             pools.insert(id, tenant_pool);
         }
 
         web::Data::new(AppState {
-            pools: Arc::new(Mutex::new(pools)),
+            pools,
+            shared_pool: None,
         })
     }
 
@@ -93,7 +87,7 @@ mod tests {
         cleanup_idle_tenant_pools(&state, 30).await;
 
         // Verify results
-        let pools = state.pools.lock().await;
+        let pools = &state.pools;
         assert_eq!(pools.len(), 1, "Should only retain one pool");
         assert!(
             pools.contains_key(&tenant2),
@@ -119,7 +113,7 @@ mod tests {
         cleanup_idle_tenant_pools(&state, 30).await;
 
         // Verify all pools are kept
-        let pools = state.pools.lock().await;
+        let pools = &state.pools;
         assert_eq!(pools.len(), 2, "Should retain all pools");
         assert!(pools.contains_key(&tenant1));
         assert!(pools.contains_key(&tenant2));
@@ -141,10 +135,32 @@ mod tests {
         cleanup_idle_tenant_pools(&state, 30).await;
 
         // Verify all pools are removed
-        let pools = state.pools.lock().await;
+        let pools = &state.pools;
         assert_eq!(pools.len(), 0, "Should remove all pools");
     }
 
+    #[tokio::test]
+    async fn test_cleanup_keeps_idle_warm_pools() {
+        let now = Utc::now();
+        let old_time = now - ChronoDuration::seconds(100);
+
+        let warm_tenant = Uuid::new_v4();
+        let cold_tenant = Uuid::new_v4();
+
+        let state = create_test_state(vec![(cold_tenant, old_time)]).await;
+        state.pools.insert(
+            warm_tenant,
+            TenantPool::with_last_accessed(MockPool::into_pg_pool(), old_time).with_keep_warm(true),
+        );
+
+        // Run cleanup with 30 second idle duration
+        cleanup_idle_tenant_pools(&state, 30).await;
+
+        let pools = &state.pools;
+        assert_eq!(pools.len(), 1, "Only the warm pool should be retained");
+        assert!(pools.contains_key(&warm_tenant));
+    }
+
     #[tokio::test]
     async fn test_cleanup_with_empty_pools() {
         let state = create_test_state(vec![]).await;
@@ -153,7 +169,7 @@ mod tests {
         cleanup_idle_tenant_pools(&state, 30).await;
 
         // Verify state
-        let pools = state.pools.lock().await;
+        let pools = &state.pools;
         assert_eq!(pools.len(), 0, "Empty pools should remain empty");
     }
 }
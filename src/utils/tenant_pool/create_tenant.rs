@@ -0,0 +1,123 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, TENANT_BASE_ROLE};
+use crate::utils::{encrypt_tenant_password, generate_password};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Postgres truncates identifiers longer than this
+const MAX_ROLE_NAME_LENGTH: usize = 63;
+
+/// Creates a tenant together with its database role, see `create_tenant_with_role(..)`.
+///
+/// The role gets a freshly generated password which is only ever stored encrypted, bound to
+/// the tenant's id. The id only exists once the row is inserted, so the ciphertext is replaced
+/// by the bound one before the transaction commits.
+/// `create_tenant_with_role(..)` upserts on the tenant name, so existing tenants are
+/// rejected first rather than having their credentials replaced.
+#[tracing::instrument(name = "Creating tenant.", skip(pool, configuration))]
+pub async fn create_tenant(
+    name: &str,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<Uuid, AppError> {
+    validate_tenant_name(name).map_err(AppError::BadRequestError)?;
+
+    let db_password = generate_password();
+
+    let mut transaction = pool.begin().await?;
+
+    // Serialises concurrent requests for the same name until the row is committed
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(name)
+        .execute(&mut *transaction)
+        .await?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM tenants WHERE name = $1)")
+        .bind(name)
+        .fetch_one(&mut *transaction)
+        .await?;
+    if exists {
+        return Err(AppError::ConflictError(format!(
+            "Tenant {} already exists",
+            name
+        )));
+    }
+
+    // Only a placeholder, `db_password_encrypted` is non null and unique
+    let tenant_id: Uuid = sqlx::query_scalar("SELECT create_tenant_with_role($1, $2, $3, $4)")
+        .bind(TENANT_BASE_ROLE)
+        .bind(name)
+        .bind(db_password.expose_secret())
+        .bind(format!("pending:{}", Uuid::new_v4()))
+        .fetch_one(&mut *transaction)
+        .await?;
+
+    let db_user: String = sqlx::query_scalar("SELECT db_user FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+    sqlx::query("UPDATE tenants SET db_password_encrypted = $2 WHERE id = $1")
+        .bind(tenant_id)
+        .bind(encrypt_tenant_password(
+            &db_password,
+            &tenant_id,
+            &db_user,
+            configuration,
+        )?)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    tracing::info!(tenant_id = %tenant_id, "Created tenant");
+
+    Ok(tenant_id)
+}
+
+/// Tenant names become part of the role name (`tenant_base_<name>`).
+pub fn validate_tenant_name(name: &str) -> Result<(), String> {
+    let max_length = MAX_ROLE_NAME_LENGTH - TENANT_BASE_ROLE.len() - 1;
+
+    if name.is_empty() || name.len() > max_length {
+        return Err(format!(
+            "Tenant name must be between 1 and {} characters",
+            max_length
+        ));
+    }
+
+    if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Err("Tenant name must start with a lowercase letter".to_string());
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(
+            "Tenant name may only contain lowercase letters, digits and underscores".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_tenant_name() {
+        assert!(validate_tenant_name("alex").is_ok());
+        assert!(validate_tenant_name("acme_2024").is_ok());
+        assert!(validate_tenant_name(&"a".repeat(51)).is_ok());
+
+        assert!(validate_tenant_name("").is_err());
+        assert!(validate_tenant_name(&"a".repeat(52)).is_err());
+        assert!(validate_tenant_name("2024_acme").is_err());
+        assert!(validate_tenant_name("_acme").is_err());
+        assert!(validate_tenant_name("Acme").is_err());
+        assert!(validate_tenant_name("acme-corp").is_err());
+        assert!(validate_tenant_name("acme\"; DROP ROLE tenant_base; --").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::models::{AppState, TenantStatus};
+use crate::utils::delete_tenant;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Hard deletes every tenant pending deletion for longer than `grace_period`, see [`delete_tenant`].
+///
+/// Returns the number of deleted tenants. Failures are logged and, as the tenant is left
+/// `deleted`, retried on the next run.
+#[tracing::instrument(name = "Deleting expired tenants", skip_all)]
+pub async fn delete_expired_tenants(
+    state: &AppState,
+    pool: &PgPool,
+    grace_period: Duration,
+) -> usize {
+    let tenant_ids = match sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id
+        FROM tenants
+        WHERE status IN ($1, $2)
+          AND status_changed_at < NOW() - make_interval(secs => $3)
+        ORDER BY status_changed_at
+        "#,
+    )
+    .bind(TenantStatus::PendingDeletion.as_str())
+    .bind(TenantStatus::Deleted.as_str())
+    .bind(grace_period.as_secs_f64())
+    .fetch_all(pool)
+    .await
+    {
+        Ok(tenant_ids) => tenant_ids,
+        Err(e) => {
+            tracing::warn!("Failed to load tenants due for deletion: {}", e);
+            return 0;
+        }
+    };
+
+    let mut deleted = 0;
+    for tenant_id in tenant_ids {
+        match delete_tenant(
+            &tenant_id,
+            &[TenantStatus::PendingDeletion, TenantStatus::Deleted],
+            state,
+            pool,
+        )
+        .await
+        {
+            Ok(_) => deleted += 1,
+            Err(e) => tracing::warn!(
+                tenant_id = %tenant_id,
+                error = %e,
+                "Failed to hard delete tenant"
+            ),
+        }
+    }
+
+    deleted
+}
+
+// These run against the development database: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::{TenantPoolRegistry, TENANT_BASE_ROLE};
+    use crate::utils::{create_tenant, get_pool_for_tenant, update_tenant_status};
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use sqlx::postgres::PgPoolOptions;
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_tenant_lifecycle_ends_with_hard_deletion() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let state = AppState {
+            pools: TenantPoolRegistry::new(),
+            shared_pool: None,
+        };
+
+        let name = format!("lifecycle_{}", Uuid::new_v4().simple());
+        let db_user = format!("{}_{}", TENANT_BASE_ROLE, name);
+        let tenant_id = create_tenant(&name, &pool, &configuration).await.unwrap();
+        let tenant_pool = get_pool_for_tenant(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO users (first_name, last_name) VALUES ('Lifecycle', 'Test')")
+            .execute(tenant_pool.as_ref())
+            .await
+            .unwrap();
+
+        // Suspended tenants lose their pool and get no new one
+        update_tenant_status(&tenant_id, TenantStatus::Suspended, &state, &pool)
+            .await
+            .unwrap();
+        let suspended = get_pool_for_tenant(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap_err();
+
+        assert_eq!(suspended.status_code(), StatusCode::FORBIDDEN);
+        assert!(tenant_pool.is_closed());
+
+        // Still within the grace period, nothing is deleted
+        update_tenant_status(&tenant_id, TenantStatus::PendingDeletion, &state, &pool)
+            .await
+            .unwrap();
+        let pending = get_pool_for_tenant(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap_err();
+        delete_expired_tenants(&state, &pool, Duration::from_secs(3600)).await;
+
+        assert_eq!(pending.status_code(), StatusCode::GONE);
+
+        // Past it, the row, its users and its role are gone
+        let deleted = delete_expired_tenants(&state, &pool, Duration::ZERO).await;
+        let tenants: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let roles: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pg_roles WHERE rolname = $1")
+            .bind(&db_user)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let audited: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM tenant_deletions WHERE tenant_id = $1")
+                .bind(tenant_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+
+        assert_eq!(deleted, 1);
+        assert_eq!(tenants, 0);
+        assert_eq!(users, 0);
+        assert_eq!(roles, 0);
+        assert_eq!(audited, 1);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::models::{AppError, AppState, TenantDeletionRecord, TenantStatus, TENANT_BASE_ROLE};
+use crate::utils::forget_tenant_pools;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Whether the role of the `tenants` row owns a database, which would keep it from being dropped.
+const OWNS_DATABASE: &str = r#"EXISTS (
+    SELECT 1
+    FROM pg_database
+    WHERE datdba = (SELECT oid FROM pg_roles WHERE rolname = tenants.db_user)
+)"#;
+
+/// Offboards a tenant whose status is one of `statuses`, see `delete_tenant_with_role(..)`.
+///
+/// A tenant whose role still owns a dedicated database is refused with a `409` and left as
+/// it is, as its role couldn't be dropped. Otherwise the tenant is marked `deleted` and its
+/// cached pool closed first, so that it stays unreachable should the deletion itself fail.
+/// The database then ends the role's remaining sessions, revokes its grants, drops it and
+/// deletes the tenant's rows in one transaction. A failed deletion leaves the tenant
+/// `deleted` and is retried by calling this again.
+#[tracing::instrument(
+    name = "Deleting tenant.",
+    fields(tenant_id = %tenant_id),
+    skip(statuses, state, pool)
+)]
+pub async fn delete_tenant(
+    tenant_id: &Uuid,
+    statuses: &[TenantStatus],
+    state: &AppState,
+    pool: &PgPool,
+) -> Result<TenantDeletionRecord, AppError> {
+    let statuses: Vec<&str> = statuses.iter().map(TenantStatus::as_str).collect();
+
+    let name: Option<String> = sqlx::query_scalar(&format!(
+        r#"
+        UPDATE tenants
+        SET status = $2,
+            status_changed_at = CASE WHEN status = $2 THEN status_changed_at ELSE CURRENT_TIMESTAMP END
+        WHERE id = $1 AND status = ANY($3) AND NOT {}
+        RETURNING name
+        "#,
+        OWNS_DATABASE
+    ))
+    .bind(tenant_id)
+    .bind(TenantStatus::Deleted.as_str())
+    .bind(&statuses)
+    .fetch_optional(pool)
+    .await?;
+
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let refused: Option<(String, bool, bool)> = sqlx::query_as(&format!(
+                "SELECT name, status = ANY($2), {} FROM tenants WHERE id = $1",
+                OWNS_DATABASE
+            ))
+            .bind(tenant_id)
+            .bind(&statuses)
+            .fetch_optional(pool)
+            .await?;
+
+            return Err(match refused {
+                Some((name, true, true)) => AppError::ConflictError(format!(
+                    "Tenant {} can't be deleted, its role still owns a database: drop it first",
+                    name
+                )),
+                Some(_) => AppError::ConflictError(format!(
+                    "Only tenants which are {} can be deleted",
+                    statuses.join(" or ")
+                )),
+                None => AppError::NotFoundError,
+            });
+        }
+    };
+
+    forget_tenant_pools(tenant_id, TenantStatus::Deleted, state);
+
+    let deletion =
+        sqlx::query_as::<_, TenantDeletionRecord>("SELECT * FROM delete_tenant_with_role($1, $2)")
+            .bind(TENANT_BASE_ROLE)
+            .bind(&name)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    tenant_name = name,
+                    error = %e,
+                    "Failed to delete tenant, it is left deleted until the deletion is retried"
+                );
+                match e.as_database_error().and_then(|e| e.code()).as_deref() {
+                    // `dependent_objects_still_exist`, a database was created since the check above
+                    Some("2BP01") => AppError::ConflictError(format!(
+                        "Tenant {} is left deleted, its role owns a database: drop it and retry",
+                        name
+                    )),
+                    _ => AppError::from(e),
+                }
+            })?
+            .ok_or(AppError::NotFoundError)?;
+
+    tracing::info!(
+        deletion_id = %deletion.id,
+        tenant_name = deletion.tenant_name,
+        db_user = deletion.db_user,
+        terminated_backends = deletion.terminated_backends,
+        "Deleted tenant and its role"
+    );
+
+    Ok(deletion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::TenantPoolRegistry;
+    use crate::utils::{create_tenant, get_pool_for_tenant};
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use sqlx::postgres::PgPoolOptions;
+
+    // Runs against the development database: `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_refused_deletion_leaves_tenant_untouched() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let state = AppState {
+            pools: TenantPoolRegistry::new(),
+            shared_pool: None,
+        };
+        let name = format!("owning_{}", Uuid::new_v4().simple());
+        let db_user = format!("{}_{}", TENANT_BASE_ROLE, name);
+        let tenant_id = create_tenant(&name, &pool, &configuration).await.unwrap();
+        let tenant_pool = get_pool_for_tenant(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        sqlx::query(&format!(
+            "CREATE DATABASE \"{}\" OWNER \"{}\"",
+            name, db_user
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let refused = delete_tenant(&tenant_id, &TenantStatus::ALL, &state, &pool)
+            .await
+            .unwrap_err();
+        let status: String = sqlx::query_scalar("SELECT status FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let users: Result<i64, _> = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(tenant_pool.as_ref())
+            .await;
+
+        assert_eq!(refused.status_code(), StatusCode::CONFLICT);
+        assert_eq!(status, TenantStatus::Active.as_str());
+        assert!(users.is_ok());
+        assert!(state.pools.get(&tenant_id).is_some());
+
+        sqlx::query(&format!("DROP DATABASE \"{}\"", name))
+            .execute(&pool)
+            .await
+            .unwrap();
+        delete_tenant(&tenant_id, &TenantStatus::ALL, &state, &pool)
+            .await
+            .unwrap();
+    }
+}
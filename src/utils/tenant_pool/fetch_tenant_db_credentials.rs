@@ -1,7 +1,7 @@
 use crate::configurations::Configuration;
-use crate::models::{Tenant, TenantCredentials};
-use crate::utils::decrypt_aes_gcm;
-use secrecy::{ExposeSecret, SecretString};
+use crate::models::{AppError, Tenant, TenantCredentials, TenantIsolationMode, TenantStatus};
+use crate::utils::decrypt_tenant_password;
+use anyhow::anyhow;
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -14,17 +14,34 @@ pub async fn fetch_tenant_db_credentials(
     tenant_id: &Uuid,
     pool: &PgPool,
     configuration: &Configuration,
-) -> Result<TenantCredentials, Box<dyn std::error::Error>> {
+) -> Result<TenantCredentials, AppError> {
     let tenant = sqlx::query_as!(Tenant, "SELECT * FROM tenants WHERE id = $1", tenant_id)
         .fetch_one(pool)
         .await?;
 
-    let decryption_key = configuration.secrets.aes256_gcm_key.expose_secret();
-    let db_password_encrypted = tenant.db_password_encrypted.unwrap();
-    let db_password_plaintext = decrypt_aes_gcm(decryption_key, db_password_encrypted.as_str())?;
+    let pool_settings = tenant.pool_settings();
+    let database = tenant.database_settings();
+    let isolation_mode = TenantIsolationMode::try_from(tenant.isolation_mode)
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+    let status =
+        TenantStatus::try_from(tenant.status).map_err(|e| AppError::InternalError(anyhow!(e)))?;
+    let db_password_encrypted = tenant.db_password_encrypted.unwrap_or_default();
+    // Whichever keyring key the ciphertext names, bound to this very tenant
+    let db_password = decrypt_tenant_password(
+        &db_password_encrypted,
+        tenant_id,
+        &tenant.db_user,
+        configuration,
+    )?;
 
     Ok(TenantCredentials {
         db_user: tenant.db_user,
-        db_password: SecretString::from(db_password_plaintext),
+        db_password,
+        pool_settings,
+        warm: tenant.warm,
+        isolation_mode,
+        database,
+        schema: tenant.db_schema,
+        status,
     })
 }
@@ -1,15 +1,23 @@
 use crate::configurations::{Configuration, DatabaseConfiguration};
-use crate::models::{AppState, TenantPool};
-use crate::utils::fetch_tenant_db_credentials;
-use actix_web::HttpResponse;
-use chrono::Utc;
-use sqlx::postgres::PgPoolOptions;
+use crate::models::{
+    AppError, AppState, EvictionReason, ReservedTenantPool, TenantDatabaseSettings,
+    TenantIsolationMode, TenantPool, TenantPoolSettings, TenantStatus,
+};
+use crate::utils::{fetch_tenant_db_credentials, with_search_path};
+use anyhow::anyhow;
+use secrecy::SecretString;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::PgPool;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
 use uuid::Uuid;
 
+/// The tenant's own pool, built on first use.
+///
+/// A cached pool which can no longer connect is only noticed once a connection is taken from
+/// it, [`begin_tenant_transaction`](crate::utils::begin_tenant_transaction) then rebuilds it.
+/// With a shared pool configured, tenants of the shared database get none and are refused,
+/// their connections go through [`begin_tenant_transaction`](crate::utils::begin_tenant_transaction).
 #[tracing::instrument(
     name = "Fetching connection pool for tenant.",
     fields(tenant_id = %tenant_id),
@@ -20,84 +28,349 @@ pub async fn get_pool_for_tenant(
     state: &AppState,
     pool: &PgPool,
     configuration: &Configuration,
-) -> Result<Arc<PgPool>, HttpResponse> {
-    let mut pools = state.pools.lock().await;
-    // Check if the tenant's pool already exists
-    if let Some(tenant_pool) = pools.get_mut(tenant_id) {
-        // Lock the Mutex to mutate the TenantPool
-        let mut tenant_pool = tenant_pool.lock().await;
-
+) -> Result<Arc<PgPool>, AppError> {
+    // Fast path: the tenant's pool already exists
+    if let Some(tenant_pool) = state.pools.get(tenant_id) {
         // Mutate the last accessed (visited) time
-        tenant_pool.last_accessed = Utc::now();
+        tenant_pool.touch();
 
         return Ok(Arc::clone(&tenant_pool.pool));
     }
 
-    // Fetch credentials for the tenant's database
-    let credentials = fetch_tenant_db_credentials(tenant_id, pool, configuration)
+    // Slow path: concurrent callers for the same tenant share this build,
+    // other tenants are never blocked by it
+    let tenant_pool = state
+        .pools
+        .get_or_try_init(*tenant_id, || {
+            build_tenant_pool(tenant_id, state, pool, configuration)
+        })
+        .await?;
+
+    ensure_tenant_still_active(tenant_id, &tenant_pool, state, pool).await?;
+    tenant_pool.touch();
+
+    Ok(Arc::clone(&tenant_pool.pool))
+}
+
+/// Evicts a broken pool and builds a new one, fetching the tenant's credentials again.
+/// The rebuild is attempted once, its failure is returned to the caller.
+pub(crate) async fn rebuild_tenant_pool(
+    tenant_id: &Uuid,
+    broken: &Arc<TenantPool>,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<Arc<TenantPool>, AppError> {
+    let tenant_pool = state
+        .pools
+        .rebuild(*tenant_id, broken, || {
+            build_tenant_pool(tenant_id, state, pool, configuration)
+        })
+        .await?;
+
+    ensure_tenant_still_active(tenant_id, &tenant_pool, state, pool).await?;
+    tenant_pool.touch();
+
+    Ok(tenant_pool)
+}
+
+/// Checks the tenant's status again once its new pool is registered, evicting the pool
+/// when the tenant is no longer active.
+///
+/// A status change committed while the pool was being built has already dropped the
+/// tenant's pools, before this one was registered. Reading the status after registering
+/// means either this check or that eviction sees the pool.
+pub(crate) async fn ensure_tenant_still_active(
+    tenant_id: &Uuid,
+    tenant_pool: &Arc<TenantPool>,
+    state: &AppState,
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    let status: Option<String> = sqlx::query_scalar("SELECT status FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?;
+    let active = match status {
+        Some(status) => TenantStatus::try_from(status)
+            .map_err(|e| AppError::InternalError(anyhow!(e)))?
+            .ensure_active(),
+        None => Err(AppError::NotFoundError),
+    };
+
+    if active.is_err() {
+        state
+            .pools
+            .evict(tenant_id, tenant_pool, EvictionReason::Deactivated);
+    }
+
+    active
+}
+
+/// Builds a pool for the tenant, along with the capacity reserved for it. The registry
+/// releases the reservation once the pool is registered.
+pub(crate) async fn build_tenant_pool<'a>(
+    tenant_id: &Uuid,
+    state: &'a AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<ReservedTenantPool<'a>, AppError> {
+    // Fetch credentials (and pool overrides) for the tenant's database
+    let credentials = fetch_tenant_db_credentials(tenant_id, pool, configuration).await?;
+
+    // Suspended and deleted tenants never get a pool
+    credentials.status.ensure_active()?;
+
+    // The shared pool serves these, a pool of their own would bring back one pool per tenant
+    if state.shared_pool.is_some() && credentials.isolation_mode == TenantIsolationMode::Shared {
+        return Err(AppError::InternalError(anyhow!(
+            "Tenant {} is served by the shared pool, it gets no pool of its own",
+            tenant_id
+        )));
+    }
+
+    let mut pool_options = tenant_pool_options(&credentials.pool_settings, &configuration.database);
+
+    // Make room within the pool count and connection budget before connecting,
+    // the reservation is held until the new pool is registered
+    let reservation = state
+        .pools
+        .reserve(tenant_id, pool_options.get_max_connections())
+        .map_err(|_| AppError::TenantPoolCapacityExhausted {
+            retry_after_in_seconds: configuration.tenant_pools.retry_after_in_seconds,
+        })?;
+
+    // Dedicated tenant databases may live elsewhere, the shared database otherwise
+    let connect_options = tenant_connect_options(
+        &credentials.db_user,
+        &credentials.db_password,
+        &credentials.database,
+        &configuration.database,
+    )
+    .map_err(|e| AppError::InternalError(anyhow!("Invalid tenant database settings: {}", e)))?;
+
+    // Schema tenants share the application database, their connections are pinned to their schema
+    if let (TenantIsolationMode::Schema, Some(schema)) =
+        (credentials.isolation_mode, &credentials.schema)
+    {
+        pool_options = with_search_path(pool_options, schema);
+    }
+
+    let pool = pool_options
+        .connect_with(connect_options)
         .await
-        .map_err(|_| {
-            HttpResponse::InternalServerError().body("Failed to fetch tenant credentials")
+        .map_err(|e| {
+            AppError::InternalError(anyhow!("Failed to create dedicated tenant pool: {}", e))
         })?;
 
-    let database_configuration = DatabaseConfiguration {
-        username: credentials.db_user.clone(),
-        password: credentials.db_password,
-        port: configuration.database.port,
-        host: configuration.database.host.clone(),
-        database_name: configuration.database.database_name.clone(),
-        require_ssl: configuration.database.require_ssl,
+    let keep_warm = credentials.warm || configuration.tenant_pools.warm_tenants.contains(tenant_id);
+
+    Ok(ReservedTenantPool {
+        tenant_pool: TenantPool::new(pool).with_keep_warm(keep_warm),
+        reservation: Some(reservation),
+    })
+}
+
+/// Connect options for a tenant's own credentials: its database settings first,
+/// then the application database configuration.
+pub(crate) fn tenant_connect_options(
+    db_user: &str,
+    db_password: &SecretString,
+    settings: &TenantDatabaseSettings,
+    defaults: &DatabaseConfiguration,
+) -> Result<PgConnectOptions, String> {
+    let mut connect_options = DatabaseConfiguration {
+        username: db_user.to_string(),
+        password: db_password.clone(),
+        port: settings.port.unwrap_or(defaults.port),
+        host: settings
+            .host
+            .clone()
+            .unwrap_or_else(|| defaults.host.clone()),
+        database_name: settings
+            .database_name
+            .clone()
+            .unwrap_or_else(|| defaults.database_name.clone()),
+        require_ssl: defaults.require_ssl,
         min_connections: None,
         max_connections: None,
         acquire_timeout: None,
         max_lifetime: None,
         idle_timeout: None,
-    };
-    let connect_options = database_configuration.with_db();
+    }
+    .with_db();
+
+    // Only set ssl_mode if provided
+    if let Some(ssl_mode) = settings.parsed_ssl_mode()? {
+        connect_options = connect_options.ssl_mode(ssl_mode);
+    }
+
+    Ok(connect_options)
+}
+
+/// Pool options for a tenant: its own overrides first, then the global database defaults.
+fn tenant_pool_options(
+    settings: &TenantPoolSettings,
+    defaults: &DatabaseConfiguration,
+) -> PgPoolOptions {
     let mut pool_options = PgPoolOptions::new();
 
     // Only set min_connections if provided
-    if let Some(min_connections) = configuration.database.min_connections {
+    if let Some(min_connections) = settings
+        .min_connections
+        .map(|value| value as u32)
+        .or(defaults.min_connections)
+    {
         pool_options = pool_options.min_connections(min_connections);
     }
 
     // Only set max_connections if provided
-    if let Some(max_connections) = configuration.database.max_connections {
+    if let Some(max_connections) = settings
+        .max_connections
+        .map(|value| value as u32)
+        .or(defaults.max_connections)
+    {
         pool_options = pool_options.max_connections(max_connections);
     }
 
     // Only set acquire_timeout if provided
-    if let Some(acquire_timeout) = configuration.database.acquire_timeout {
+    if let Some(acquire_timeout) = settings
+        .acquire_timeout
+        .map(|value| value as u64)
+        .or(defaults.acquire_timeout)
+    {
         pool_options = pool_options.acquire_timeout(Duration::from_millis(acquire_timeout));
     }
 
     // Only set max_lifetime if provided
-    if let Some(max_lifetime) = configuration.database.max_lifetime {
+    if let Some(max_lifetime) = settings
+        .max_lifetime
+        .map(|value| value as u64)
+        .or(defaults.max_lifetime)
+    {
         pool_options = pool_options.max_lifetime(Duration::from_secs(max_lifetime));
     }
 
     // Only set idle_timeout if provided
-    if let Some(idle_timeout) = configuration.database.idle_timeout {
+    if let Some(idle_timeout) = settings
+        .idle_timeout
+        .map(|value| value as u64)
+        .or(defaults.idle_timeout)
+    {
         pool_options = pool_options.idle_timeout(Duration::from_secs(idle_timeout));
     }
 
-    let pool = pool_options
-        .connect_with(connect_options)
-        .await
-        .map_err(|_| {
-            HttpResponse::InternalServerError().body("Failed to create dedicated tenant pool.")
-        })?;
+    pool_options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgSslMode;
+
+    fn defaults() -> DatabaseConfiguration {
+        DatabaseConfiguration {
+            username: "app".to_string(),
+            password: SecretString::from("password"),
+            host: "localhost".to_string(),
+            database_name: "app".to_string(),
+            require_ssl: false,
+            port: 5432,
+            min_connections: Some(2),
+            max_connections: Some(4),
+            acquire_timeout: Some(750),
+            max_lifetime: Some(900),
+            idle_timeout: Some(150),
+        }
+    }
+
+    #[test]
+    fn test_unset_settings_fall_back_to_defaults() {
+        let pool_options = tenant_pool_options(&TenantPoolSettings::default(), &defaults());
+
+        assert_eq!(pool_options.get_min_connections(), 2);
+        assert_eq!(pool_options.get_max_connections(), 4);
+        assert_eq!(
+            pool_options.get_acquire_timeout(),
+            Duration::from_millis(750)
+        );
+        assert_eq!(
+            pool_options.get_max_lifetime(),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(
+            pool_options.get_idle_timeout(),
+            Some(Duration::from_secs(150))
+        );
+    }
+
+    #[test]
+    fn test_tenant_settings_override_defaults() {
+        let settings = TenantPoolSettings {
+            min_connections: Some(0),
+            max_connections: Some(32),
+            acquire_timeout: None,
+            max_lifetime: Some(60),
+            idle_timeout: None,
+        };
+
+        let pool_options = tenant_pool_options(&settings, &defaults());
+
+        assert_eq!(pool_options.get_min_connections(), 0);
+        assert_eq!(pool_options.get_max_connections(), 32);
+        assert_eq!(
+            pool_options.get_acquire_timeout(),
+            Duration::from_millis(750)
+        );
+        assert_eq!(
+            pool_options.get_max_lifetime(),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            pool_options.get_idle_timeout(),
+            Some(Duration::from_secs(150))
+        );
+    }
+
+    #[test]
+    fn test_connect_options_fall_back_to_shared_database() {
+        let connect_options = tenant_connect_options(
+            "tenant_base_alex",
+            &SecretString::from("secret"),
+            &TenantDatabaseSettings::default(),
+            &defaults(),
+        )
+        .unwrap();
 
-    // Create a new `TenantPool` struct with the newly created pool (wrapped in an Arc)
-    let tenant_pool = Arc::new(Mutex::new(TenantPool {
-        pool: Arc::new(pool),
-        last_accessed: Utc::now(),
-    }));
+        assert_eq!(connect_options.get_username(), "tenant_base_alex");
+        assert_eq!(connect_options.get_host(), "localhost");
+        assert_eq!(connect_options.get_port(), 5432);
+        assert_eq!(connect_options.get_database(), Some("app"));
+        assert!(matches!(connect_options.get_ssl_mode(), PgSslMode::Prefer));
+    }
 
-    pools.insert(*tenant_id, Arc::clone(&tenant_pool));
+    #[test]
+    fn test_connect_options_use_dedicated_database() {
+        let settings = TenantDatabaseSettings {
+            host: Some("tenant-db.internal".to_string()),
+            port: Some(6432),
+            database_name: Some("tenant_alex".to_string()),
+            ssl_mode: Some("verify-full".to_string()),
+        };
 
-    // Lock the Mutex to access the pool
-    let tenant_pool = tenant_pool.lock().await;
+        let connect_options = tenant_connect_options(
+            "tenant_base_alex",
+            &SecretString::from("secret"),
+            &settings,
+            &defaults(),
+        )
+        .unwrap();
 
-    Ok(Arc::clone(&tenant_pool.pool)) // Clone the Arc to return
+        assert_eq!(connect_options.get_host(), "tenant-db.internal");
+        assert_eq!(connect_options.get_port(), 6432);
+        assert_eq!(connect_options.get_database(), Some("tenant_alex"));
+        assert!(matches!(
+            connect_options.get_ssl_mode(),
+            PgSslMode::VerifyFull
+        ));
+    }
 }
@@ -1,6 +1,8 @@
 use actix_web::{HttpRequest, HttpResponse};
 use uuid::Uuid;
 
+/// Reads the tenant id from the `x-tenant-id` header only, handlers resolve the tenant
+/// through the configured `TenantResolverChain`.
 pub fn get_tenant_id_from_request(req: &HttpRequest) -> Result<Uuid, HttpResponse> {
     match req.headers().get("x-tenant-id") {
         Some(tenant_id) => {
@@ -1,9 +1,43 @@
+mod begin_tenant_transaction;
 mod cleanup_idle_tenant_pools;
+mod create_tenant;
+mod delete_expired_tenants;
+mod delete_tenant;
 mod fetch_tenant_db_credentials;
 mod get_pool_for_tenant;
 mod get_tenant_id_from_request;
+mod probe_tenant_pools;
+mod provision_tenant_database;
+mod provision_tenant_schema;
+mod reencrypt_tenant_passwords;
+mod refresh_pool_for_tenant;
+mod rotate_tenant_credentials;
+mod tenant_credentials_rotation;
+mod tenant_deletion;
+mod tenant_password_encryption;
+mod tenant_pool_janitor;
+mod tenant_search_path;
+mod update_tenant_status;
+mod warm_up_tenant_pools;
 
+pub use begin_tenant_transaction::*;
 pub use cleanup_idle_tenant_pools::*;
+pub use create_tenant::*;
+pub use delete_expired_tenants::*;
+pub use delete_tenant::*;
 pub use fetch_tenant_db_credentials::*;
 pub use get_pool_for_tenant::*;
 pub use get_tenant_id_from_request::*;
+pub use probe_tenant_pools::*;
+pub use provision_tenant_database::*;
+pub use provision_tenant_schema::*;
+pub use reencrypt_tenant_passwords::*;
+pub use refresh_pool_for_tenant::*;
+pub use rotate_tenant_credentials::*;
+pub use tenant_credentials_rotation::*;
+pub use tenant_deletion::*;
+pub use tenant_password_encryption::*;
+pub use tenant_pool_janitor::*;
+pub use tenant_search_path::*;
+pub use update_tenant_status::*;
+pub use warm_up_tenant_pools::*;
@@ -0,0 +1,203 @@
+use crate::configurations::Configuration;
+use crate::migrations::run_tenant_migrations;
+use crate::models::{
+    AppError, AppState, EvictionReason, TenantDatabaseSettings, TenantIsolationMode,
+};
+use crate::utils::{
+    fetch_tenant_db_credentials, quote_identifier, refresh_pool_for_tenant, tenant_connect_options,
+};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use tokio::net::lookup_host;
+use uuid::Uuid;
+
+/// Moves a tenant onto a database of its own.
+///
+/// When the target database lives on the application cluster it is created (owned by the
+/// tenant's role) if missing, databases on other hosts must already exist. The tenant
+/// scoped migrations are then applied with the tenant's own credentials, the `tenants` row
+/// is pointed at the new database and the cached pool, if any, is rebuilt
+/// or, failing that, evicted.
+#[tracing::instrument(
+    name = "Provisioning dedicated database for tenant.",
+    fields(tenant_id = %tenant_id),
+    skip(settings, state, pool, configuration)
+)]
+pub async fn provision_tenant_database(
+    tenant_id: &Uuid,
+    settings: &TenantDatabaseSettings,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<(), AppError> {
+    let database_name = settings
+        .database_name
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequestError("database_name is required".to_string()))?;
+    settings
+        .parsed_ssl_mode()
+        .map_err(AppError::BadRequestError)?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM tenants WHERE id = $1)")
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFoundError);
+    }
+
+    let credentials = fetch_tenant_db_credentials(tenant_id, pool, configuration).await?;
+
+    if is_on_application_cluster(settings, configuration).await {
+        create_database_if_missing(database_name, &credentials.db_user, pool).await?;
+    }
+
+    let connect_options = tenant_connect_options(
+        &credentials.db_user,
+        &credentials.db_password,
+        settings,
+        &configuration.database,
+    )
+    .map_err(AppError::BadRequestError)?;
+    let tenant_database_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect_with(connect_options)
+        .await?;
+
+    let migrated = run_tenant_migrations(&tenant_database_pool).await;
+    tenant_database_pool.close().await;
+    migrated?;
+
+    sqlx::query(
+        r#"
+        UPDATE tenants
+        SET isolation_mode = $2,
+            db_host = $3,
+            db_port = $4,
+            db_name = $5,
+            db_ssl_mode = $6,
+            db_schema = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(TenantIsolationMode::Database.as_str())
+    .bind(&settings.host)
+    .bind(settings.port.map(i32::from))
+    .bind(database_name)
+    .bind(&settings.ssl_mode)
+    .execute(pool)
+    .await?;
+
+    tracing::info!(database_name, "Provisioned dedicated tenant database");
+
+    // Requests already holding the previous pool finish against the shared database
+    // The tenant is provisioned at this point, a pool that can't be rebuilt right now (e.g.
+    // the registry being at capacity) is dropped instead and built again on the next request
+    if let Err(e) = refresh_pool_for_tenant(
+        tenant_id,
+        state,
+        pool,
+        configuration,
+        EvictionReason::Reconfigured,
+    )
+    .await
+    {
+        tracing::warn!(error = %e, "Failed to rebuild the tenant pool, evicting it");
+        if let Some(stale) = state.pools.get(tenant_id) {
+            state
+                .pools
+                .evict(tenant_id, &stale, EvictionReason::Reconfigured);
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the settings point at the application's own cluster, comparing the addresses both
+/// hosts resolve to so that e.g. `localhost` and `127.0.0.1` are the same cluster.
+pub(crate) async fn is_on_application_cluster(
+    settings: &TenantDatabaseSettings,
+    configuration: &Configuration,
+) -> bool {
+    let application = (
+        configuration.database.host.as_str(),
+        configuration.database.port,
+    );
+    let tenant = (
+        settings.host.as_deref().unwrap_or(application.0),
+        settings.port.unwrap_or(application.1),
+    );
+
+    if tenant == application {
+        return true;
+    }
+
+    match (lookup_host(application).await, lookup_host(tenant).await) {
+        (Ok(application), Ok(tenant)) => {
+            let application: Vec<SocketAddr> = application.collect();
+            tenant
+                .into_iter()
+                .any(|address| application.contains(&address))
+        }
+        // Unresolvable hosts are never taken for the application cluster
+        _ => false,
+    }
+}
+
+async fn create_database_if_missing(
+    database_name: &str,
+    owner: &str,
+    pool: &PgPool,
+) -> Result<(), AppError> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(database_name)
+            .fetch_one(pool)
+            .await?;
+
+    if exists {
+        return Ok(());
+    }
+
+    // Utility statements can't be parameterised, identifiers are quoted instead
+    sqlx::query(&format!(
+        "CREATE DATABASE {} OWNER {}",
+        quote_identifier(database_name),
+        quote_identifier(owner)
+    ))
+    .execute(pool)
+    .await?;
+
+    tracing::info!(database_name, "Created dedicated tenant database");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+
+    #[tokio::test]
+    async fn test_application_cluster_is_matched_by_address() {
+        let mut configuration = get_configuration().expect("Failed to read configuration.");
+        configuration.database.host = "localhost".to_string();
+        configuration.database.port = 5432;
+        let settings = |host: &str, port| TenantDatabaseSettings {
+            host: Some(host.to_string()),
+            port: Some(port),
+            ..TenantDatabaseSettings::default()
+        };
+
+        assert!(
+            is_on_application_cluster(&TenantDatabaseSettings::default(), &configuration).await
+        );
+        assert!(is_on_application_cluster(&settings("127.0.0.1", 5432), &configuration).await);
+        assert!(!is_on_application_cluster(&settings("127.0.0.1", 6432), &configuration).await);
+        assert!(
+            !is_on_application_cluster(&settings("tenant-db.invalid", 5432), &configuration).await
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::configurations::Configuration;
+use crate::migrations::run_tenant_migrations;
+use crate::models::{
+    AppError, AppState, EvictionReason, TenantDatabaseSettings, TenantIsolationMode,
+};
+use crate::utils::{
+    fetch_tenant_db_credentials, quote_identifier, refresh_pool_for_tenant, tenant_connect_options,
+    validate_schema_name, with_search_path,
+};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Moves a tenant onto a schema of its own, within the application database.
+///
+/// The schema is created (owned by the tenant's role) if missing, an existing schema owned
+/// by any other role is refused with a `409`. The tenant scoped migrations are applied into
+/// it with the tenant's own credentials. The `tenants` row then records the schema and the
+/// cached pool, if any, is rebuilt or, failing that, evicted.
+#[tracing::instrument(
+    name = "Provisioning dedicated schema for tenant.",
+    fields(tenant_id = %tenant_id),
+    skip(state, pool, configuration)
+)]
+pub async fn provision_tenant_schema(
+    tenant_id: &Uuid,
+    schema: &str,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<(), AppError> {
+    validate_schema_name(schema).map_err(AppError::BadRequestError)?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM tenants WHERE id = $1)")
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFoundError);
+    }
+
+    let credentials = fetch_tenant_db_credentials(tenant_id, pool, configuration).await?;
+
+    // `IF NOT EXISTS` would otherwise silently point the tenant at another role's schema
+    let owner: Option<String> = sqlx::query_scalar(
+        "SELECT pg_get_userbyid(nspowner)::text FROM pg_namespace WHERE nspname = $1",
+    )
+    .bind(schema)
+    .fetch_optional(pool)
+    .await?;
+    if owner.is_some_and(|owner| owner != credentials.db_user) {
+        return Err(AppError::ConflictError(format!(
+            "Schema {} already exists and isn't owned by the tenant",
+            schema
+        )));
+    }
+
+    // Utility statements can't be parameterised, identifiers are quoted instead
+    sqlx::query(&format!(
+        "CREATE SCHEMA IF NOT EXISTS {} AUTHORIZATION {}",
+        quote_identifier(schema),
+        quote_identifier(&credentials.db_user)
+    ))
+    .execute(pool)
+    .await?;
+
+    // The migrations bookkeeping table lands in the tenant's schema as well
+    let connect_options = tenant_connect_options(
+        &credentials.db_user,
+        &credentials.db_password,
+        &TenantDatabaseSettings::default(),
+        &configuration.database,
+    )
+    .map_err(AppError::BadRequestError)?;
+    let tenant_schema_pool = with_search_path(PgPoolOptions::new().max_connections(1), schema)
+        .connect_with(connect_options)
+        .await?;
+
+    let migrated = run_tenant_migrations(&tenant_schema_pool).await;
+    tenant_schema_pool.close().await;
+    migrated?;
+
+    sqlx::query(
+        r#"
+        UPDATE tenants
+        SET isolation_mode = $2,
+            db_schema = $3,
+            db_host = NULL,
+            db_port = NULL,
+            db_name = NULL,
+            db_ssl_mode = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(TenantIsolationMode::Schema.as_str())
+    .bind(schema)
+    .execute(pool)
+    .await?;
+
+    tracing::info!(schema, "Provisioned dedicated tenant schema");
+
+    // The tenant is provisioned at this point, a pool that can't be rebuilt right now (e.g.
+    // the registry being at capacity) is dropped instead and built again on the next request
+    if let Err(e) = refresh_pool_for_tenant(
+        tenant_id,
+        state,
+        pool,
+        configuration,
+        EvictionReason::Reconfigured,
+    )
+    .await
+    {
+        tracing::warn!(error = %e, "Failed to rebuild the tenant pool, evicting it");
+        if let Some(stale) = state.pools.get(tenant_id) {
+            state
+                .pools
+                .evict(tenant_id, &stale, EvictionReason::Reconfigured);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use crate::configurations::Configuration;
+use crate::models::AppError;
+use crate::utils::{decrypt_tenant_password, encrypt_tenant_password, VersionedCiphertext};
+use anyhow::anyhow;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const REENCRYPTION_BATCH_SIZE: i64 = 100;
+
+/// Outcome of [`reencrypt_tenant_passwords`].
+#[derive(Serialize, Debug, Default)]
+pub struct TenantPasswordReencryption {
+    pub active_key_id: String,
+    pub reencrypted: usize,
+    /// Tenants whose password couldn't be decrypted, e.g. as its key left the keyring.
+    pub failed: Vec<Uuid>,
+    /// Other passwords still not on the active key once done, e.g. skipped while a credential
+    /// rotation held their row. Running the re-encryption again picks them up.
+    pub remaining: i64,
+}
+
+/// Re-encrypts every `tenants.db_password_encrypted` not yet encrypted with the active key,
+/// naming and bound to its tenant. This is also how `v2` ciphertexts come to name their tenant
+/// and how ciphertexts written before they were bound get migrated, the latter as long as
+/// `secrets.accept_unbound_ciphertexts` is set.
+///
+/// Rows are locked and updated in batches, each in its own transaction, so credential
+/// rotations running meanwhile wait for the batch instead of being overwritten. The
+/// passwords themselves don't change and cached pools are left alone. Locked rows are
+/// skipped rather than waited for, they are counted as `remaining` at the end.
+#[tracing::instrument(name = "Re-encrypting tenant passwords.", skip_all)]
+pub async fn reencrypt_tenant_passwords(
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<TenantPasswordReencryption, AppError> {
+    let secrets = configuration.secrets.current();
+    let (active_key_id, _) = secrets
+        .active_encryption_key()
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+    let active_prefix = VersionedCiphertext::prefix(active_key_id);
+    let mut reencryption = TenantPasswordReencryption {
+        active_key_id: active_key_id.to_string(),
+        ..TenantPasswordReencryption::default()
+    };
+
+    loop {
+        let mut transaction = pool.begin().await?;
+
+        let batch = sqlx::query_as::<_, (Uuid, String, String)>(
+            r#"
+            SELECT id, db_user, db_password_encrypted
+            FROM tenants
+            WHERE db_password_encrypted IS NOT NULL
+              AND left(db_password_encrypted, length($1)) <> $1
+              AND id <> ALL($2)
+            ORDER BY id
+            LIMIT $3
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(&active_prefix)
+        .bind(&reencryption.failed)
+        .bind(REENCRYPTION_BATCH_SIZE)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        if batch.is_empty() {
+            break;
+        }
+
+        for (tenant_id, db_user, db_password_encrypted) in batch {
+            let db_password = match decrypt_tenant_password(
+                &db_password_encrypted,
+                &tenant_id,
+                &db_user,
+                configuration,
+            ) {
+                Ok(db_password) => db_password,
+                Err(e) => {
+                    tracing::warn!(
+                        tenant_id = %tenant_id,
+                        error = %e,
+                        "Failed to decrypt tenant password"
+                    );
+                    reencryption.failed.push(tenant_id);
+                    continue;
+                }
+            };
+
+            sqlx::query("UPDATE tenants SET db_password_encrypted = $2 WHERE id = $1")
+                .bind(tenant_id)
+                .bind(encrypt_tenant_password(
+                    &db_password,
+                    &tenant_id,
+                    &db_user,
+                    configuration,
+                )?)
+                .execute(&mut *transaction)
+                .await?;
+            reencryption.reencrypted += 1;
+        }
+
+        transaction.commit().await?;
+    }
+
+    reencryption.remaining = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM tenants
+        WHERE db_password_encrypted IS NOT NULL
+          AND left(db_password_encrypted, length($1)) <> $1
+          AND id <> ALL($2)
+        "#,
+    )
+    .bind(&active_prefix)
+    .bind(&reencryption.failed)
+    .fetch_one(pool)
+    .await?;
+
+    if reencryption.remaining > 0 {
+        tracing::warn!(
+            remaining = reencryption.remaining,
+            "Tenant passwords were skipped while locked, re-encrypt again to finish"
+        );
+    }
+
+    tracing::info!(
+        active_key_id = %reencryption.active_key_id,
+        reencrypted = reencryption.reencrypted,
+        failed = reencryption.failed.len(),
+        remaining = reencryption.remaining,
+        "Re-encrypted tenant passwords"
+    );
+
+    Ok(reencryption)
+}
@@ -0,0 +1,39 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, AppState, EvictionReason};
+use crate::utils::{build_tenant_pool, ensure_tenant_still_active};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Rebuilds a tenant's cached pool from the current `tenants` row and swaps it in.
+///
+/// The previous pool is drained and closed in the background. When the tenant has no
+/// cached pool there is nothing to do, the next request builds one with the current data.
+#[tracing::instrument(
+    name = "Refreshing connection pool for tenant.",
+    fields(tenant_id = %tenant_id, reason = reason.as_str()),
+    skip(state, pool, configuration)
+)]
+pub async fn refresh_pool_for_tenant(
+    tenant_id: &Uuid,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+    reason: EvictionReason,
+) -> Result<(), AppError> {
+    // The shared pool looks the tenant up again on its next transaction
+    if let Some(shared_pool) = &state.shared_pool {
+        shared_pool.forget(tenant_id);
+    }
+
+    if state.pools.get(tenant_id).is_none() {
+        return Ok(());
+    }
+
+    let tenant_pool = build_tenant_pool(tenant_id, state, pool, configuration).await?;
+    state.pools.replace(*tenant_id, tenant_pool, reason);
+
+    match state.pools.get(tenant_id) {
+        Some(tenant_pool) => ensure_tenant_still_active(tenant_id, &tenant_pool, state, pool).await,
+        None => Ok(()),
+    }
+}
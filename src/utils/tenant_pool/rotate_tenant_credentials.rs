@@ -0,0 +1,217 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, AppState, EvictionReason, Tenant, TenantStatus, TENANT_BASE_ROLE};
+use crate::utils::{
+    build_tenant_pool, encrypt_tenant_password, ensure_tenant_still_active, generate_password,
+    is_on_application_cluster,
+};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Gives the tenant's role a new generated password, see `update_tenant_password(..)`.
+///
+/// A cached pool is taken out before the change is committed and replaced by one built with
+/// the new password, requests for the tenant meanwhile wait for it, see
+/// [`TenantPoolRegistry::replace_with`](crate::models::TenantPoolRegistry::replace_with).
+/// Connections the previous pool already opened stay authenticated while it drains. Tenants
+/// whose database lives on another cluster are skipped, their role is managed there.
+#[tracing::instrument(
+    name = "Rotating credentials for tenant.",
+    fields(tenant_id = %tenant_id),
+    skip(state, pool, configuration)
+)]
+pub async fn rotate_tenant_credentials(
+    tenant_id: &Uuid,
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+) -> Result<DateTime<Utc>, AppError> {
+    let tenant = sqlx::query_as::<_, Tenant>("SELECT * FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::NotFoundError)?;
+
+    if !is_on_application_cluster(&tenant.database_settings(), configuration).await {
+        return Err(AppError::BadRequestError(
+            "Credentials of tenants on an external database cluster can't be rotated".to_string(),
+        ));
+    }
+
+    let db_password = generate_password();
+    let db_password_encrypted =
+        encrypt_tenant_password(&db_password, tenant_id, &tenant.db_user, configuration)?;
+
+    let mut transaction = pool.begin().await?;
+
+    let updated: bool = sqlx::query_scalar("SELECT update_tenant_password($1, $2, $3, $4)")
+        .bind(TENANT_BASE_ROLE)
+        .bind(&tenant.name)
+        .bind(db_password.expose_secret())
+        .bind(&db_password_encrypted)
+        .fetch_one(&mut *transaction)
+        .await?;
+    if !updated {
+        return Err(AppError::NotFoundError);
+    }
+
+    let rotated_at: DateTime<Utc> =
+        sqlx::query_scalar("SELECT updated_at FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+    // The shared pool looks the tenant up again on its next transaction
+    if let Some(shared_pool) = &state.shared_pool {
+        shared_pool.forget(tenant_id);
+    }
+
+    // Without a cached pool, the next request builds one with the new password
+    if state.pools.get(tenant_id).is_none() {
+        transaction.commit().await?;
+        tracing::info!("Rotated tenant credentials");
+
+        return Ok(rotated_at);
+    }
+
+    let tenant_pool = state
+        .pools
+        .replace_with(*tenant_id, EvictionReason::Rotated, || async {
+            transaction.commit().await?;
+            tracing::info!("Rotated tenant credentials");
+
+            build_tenant_pool(tenant_id, state, pool, configuration).await
+        })
+        .await
+        .inspect_err(|e| {
+            tracing::error!(error = %e, "Failed to rotate tenant credentials or rebuild its pool")
+        })?;
+
+    ensure_tenant_still_active(tenant_id, &tenant_pool, state, pool).await?;
+
+    Ok(rotated_at)
+}
+
+/// Rotates the credentials of every tenant whose password is older than `max_age`,
+/// tenants on their way to deletion are left alone.
+///
+/// Returns the number of rotated tenants, failures are logged and retried on the next run.
+#[tracing::instrument(name = "Rotating expired tenant credentials", skip_all)]
+pub async fn rotate_expired_tenant_credentials(
+    state: &AppState,
+    pool: &PgPool,
+    configuration: &Configuration,
+    max_age: Duration,
+) -> usize {
+    let tenant_ids = match sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id
+        FROM tenants
+        WHERE updated_at < NOW() - make_interval(secs => $1)
+          AND status IN ($2, $3)
+        ORDER BY updated_at
+        "#,
+    )
+    .bind(max_age.as_secs_f64())
+    .bind(TenantStatus::Active.as_str())
+    .bind(TenantStatus::Suspended.as_str())
+    .fetch_all(pool)
+    .await
+    {
+        Ok(tenant_ids) => tenant_ids,
+        Err(e) => {
+            tracing::warn!("Failed to load tenants due for rotation: {}", e);
+            return 0;
+        }
+    };
+
+    let mut rotated = 0;
+    for tenant_id in tenant_ids {
+        match rotate_tenant_credentials(&tenant_id, state, pool, configuration).await {
+            Ok(_) => rotated += 1,
+            Err(AppError::BadRequestError(reason)) => {
+                tracing::debug!(tenant_id = %tenant_id, reason, "Skipped tenant rotation")
+            }
+            Err(e) => tracing::warn!(
+                tenant_id = %tenant_id,
+                error = %e,
+                "Failed to rotate tenant credentials"
+            ),
+        }
+    }
+
+    rotated
+}
+
+// These run against the development database: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::TenantPoolRegistry;
+    use crate::utils::{create_tenant, get_pool_for_tenant};
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_rotation_replaces_credentials_and_pool() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let state = AppState {
+            pools: TenantPoolRegistry::new(),
+            shared_pool: None,
+        };
+
+        let name = format!("rotation_{}", Uuid::new_v4().simple());
+        let tenant_id = create_tenant(&name, &pool, &configuration).await.unwrap();
+        let encrypted_before: String =
+            sqlx::query_scalar("SELECT db_password_encrypted FROM tenants WHERE id = $1")
+                .bind(tenant_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let pool_before = get_pool_for_tenant(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+
+        rotate_tenant_credentials(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+
+        let encrypted_after: String =
+            sqlx::query_scalar("SELECT db_password_encrypted FROM tenants WHERE id = $1")
+                .bind(tenant_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let pool_after = get_pool_for_tenant(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        let connected: i32 = sqlx::query_scalar("SELECT 1")
+            .fetch_one(pool_after.as_ref())
+            .await
+            .unwrap();
+
+        assert_ne!(encrypted_before, encrypted_after);
+        assert!(!Arc::ptr_eq(&pool_before, &pool_after));
+        assert_eq!(connected, 1);
+        assert_eq!(state.pools.metrics().evicted_rotated, 1);
+
+        pool_after.close().await;
+        sqlx::query("DELETE FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(&format!("DROP ROLE {}_{}", TENANT_BASE_ROLE, name))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use crate::configurations::{Configuration, TenantCredentialsRotationConfiguration};
+use crate::models::AppState;
+use crate::utils::rotate_expired_tenant_credentials;
+use actix_web::web;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TenantCredentialsRotationSettings {
+    pub interval: Duration,
+    pub max_age: Duration,
+}
+
+impl From<&TenantCredentialsRotationConfiguration> for TenantCredentialsRotationSettings {
+    fn from(configuration: &TenantCredentialsRotationConfiguration) -> Self {
+        TenantCredentialsRotationSettings {
+            interval: Duration::from_secs(configuration.interval_in_seconds),
+            max_age: Duration::from_secs(configuration.max_age_in_seconds),
+        }
+    }
+}
+
+/// Background task rotating the passwords of tenants older than the configured maximum age,
+/// see [`rotate_expired_tenant_credentials`].
+pub struct TenantCredentialsRotation {
+    shutdown: watch::Sender<bool>,
+    worker: JoinHandle<()>,
+}
+
+impl TenantCredentialsRotation {
+    pub fn start(
+        state: web::Data<AppState>,
+        pool: PgPool,
+        configuration: Configuration,
+        settings: TenantCredentialsRotationSettings,
+    ) -> Self {
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let worker = tokio::spawn(rotation_loop(
+            state,
+            pool,
+            configuration,
+            settings,
+            shutdown_receiver,
+        ));
+
+        tracing::info!(
+            interval_in_seconds = settings.interval.as_secs(),
+            max_age_in_seconds = settings.max_age.as_secs(),
+            "Started tenant credentials rotation"
+        );
+
+        TenantCredentialsRotation { shutdown, worker }
+    }
+
+    /// Signals the task to stop and waits for it to finish its current run.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+
+        if let Err(e) = self.worker.await {
+            tracing::error!(
+                "Tenant credentials rotation did not shut down cleanly: {}",
+                e
+            );
+        }
+
+        tracing::info!("Stopped tenant credentials rotation");
+    }
+}
+
+async fn rotation_loop(
+    state: web::Data<AppState>,
+    pool: PgPool,
+    configuration: Configuration,
+    settings: TenantCredentialsRotationSettings,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    // Unlike the janitor, the first run happens right away to catch up on expired passwords
+    let mut interval = tokio::time::interval(settings.interval);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            // Also fires when the sender is gone
+            _ = shutdown.changed() => break,
+        }
+
+        let rotated =
+            rotate_expired_tenant_credentials(&state, &pool, &configuration, settings.max_age)
+                .await;
+        if rotated > 0 {
+            tracing::info!(rotated, "Rotated expired tenant credentials");
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::configurations::TenantPoolsConfiguration;
+use crate::models::AppState;
+use crate::utils::delete_expired_tenants;
+use actix_web::web;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TenantDeletionSettings {
+    pub interval: Duration,
+    pub grace_period: Duration,
+}
+
+impl From<&TenantPoolsConfiguration> for TenantDeletionSettings {
+    fn from(configuration: &TenantPoolsConfiguration) -> Self {
+        TenantDeletionSettings {
+            interval: Duration::from_secs(configuration.deletion_interval_in_seconds),
+            grace_period: Duration::from_secs(configuration.deletion_grace_period_in_seconds),
+        }
+    }
+}
+
+/// Background task hard deleting the tenants whose deletion grace period is over,
+/// see [`delete_expired_tenants`].
+pub struct TenantDeletion {
+    shutdown: watch::Sender<bool>,
+    worker: JoinHandle<()>,
+}
+
+impl TenantDeletion {
+    pub fn start(
+        state: web::Data<AppState>,
+        pool: PgPool,
+        settings: TenantDeletionSettings,
+    ) -> Self {
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        let worker = tokio::spawn(deletion_loop(state, pool, settings, shutdown_receiver));
+
+        tracing::info!(
+            interval_in_seconds = settings.interval.as_secs(),
+            grace_period_in_seconds = settings.grace_period.as_secs(),
+            "Started tenant deletion"
+        );
+
+        TenantDeletion { shutdown, worker }
+    }
+
+    /// Signals the task to stop and waits for it to finish its current run.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+
+        if let Err(e) = self.worker.await {
+            tracing::error!("Tenant deletion did not shut down cleanly: {}", e);
+        }
+
+        tracing::info!("Stopped tenant deletion");
+    }
+}
+
+async fn deletion_loop(
+    state: web::Data<AppState>,
+    pool: PgPool,
+    settings: TenantDeletionSettings,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(settings.interval);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            // Also fires when the sender is gone
+            _ = shutdown.changed() => break,
+        }
+
+        let deleted = delete_expired_tenants(&state, &pool, settings.grace_period).await;
+        if deleted > 0 {
+            tracing::info!(deleted, "Deleted expired tenants");
+        }
+    }
+}
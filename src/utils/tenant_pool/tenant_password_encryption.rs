@@ -0,0 +1,286 @@
+use crate::configurations::Configuration;
+use crate::models::AppError;
+use crate::utils::{encrypt_versioned_aes_gcm, VersionedCiphertext};
+use anyhow::anyhow;
+use secrecy::SecretString;
+use uuid::Uuid;
+
+/// Encrypts a tenant role password with the active key of `secrets.keyring`.
+///
+/// The ciphertext names its tenant and is bound to the tenant's id and role, copied onto
+/// another tenant's row it no longer decrypts.
+pub fn encrypt_tenant_password(
+    password: &SecretString,
+    tenant_id: &Uuid,
+    db_user: &str,
+    configuration: &Configuration,
+) -> Result<String, AppError> {
+    let secrets = configuration.secrets.current();
+    let (key_id, key) = secrets
+        .active_encryption_key()
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+
+    encrypt_versioned_aes_gcm(
+        key_id,
+        key,
+        password,
+        &tenant_id.to_string(),
+        &tenant_binding(tenant_id, db_user),
+    )
+    .map_err(|e| AppError::InternalError(anyhow!(e)))
+}
+
+/// Decrypts a tenant role password with whichever key its ciphertext names.
+///
+/// A ciphertext naming another tenant fails with [`AppError::CiphertextBindingMismatch`], as
+/// do unbound ones unless `secrets.accept_unbound_ciphertexts` is set. Any other failure, e.g.
+/// a wrong key or a corrupt ciphertext, is a decryption error.
+pub fn decrypt_tenant_password(
+    ciphertext: &str,
+    tenant_id: &Uuid,
+    db_user: &str,
+    configuration: &Configuration,
+) -> Result<SecretString, AppError> {
+    let ciphertext =
+        VersionedCiphertext::parse(ciphertext).map_err(|e| AppError::InternalError(anyhow!(e)))?;
+
+    if ciphertext
+        .bound_to
+        .is_some_and(|bound_to| bound_to != tenant_id.to_string())
+        || (!ciphertext.is_bound() && !configuration.secrets.accept_unbound_ciphertexts)
+    {
+        return Err(AppError::CiphertextBindingMismatch {
+            tenant_id: *tenant_id,
+        });
+    }
+
+    let secrets = configuration.secrets.current();
+    let key = secrets
+        .encryption_key(ciphertext.key_id)
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?;
+    let aad = match ciphertext.is_bound() {
+        true => tenant_binding(tenant_id, db_user),
+        false => Vec::new(),
+    };
+
+    key.decrypt(ciphertext.payload, &aad).map_err(|e| {
+        AppError::InternalError(anyhow!(
+            "Failed to decrypt the password of tenant {}: {}",
+            tenant_id,
+            e
+        ))
+    })
+}
+
+fn tenant_binding(tenant_id: &Uuid, db_user: &str) -> Vec<u8> {
+    format!("tenant:{}:{}", tenant_id, db_user).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::{get_configuration, Secrets};
+    use crate::utils::{generate_password, AesGcmCipher};
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use secrecy::ExposeSecret;
+
+    const PREVIOUS_KEY_HEX: &str =
+        "0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0";
+    const ACTIVE_KEY_HEX: &str = "a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90";
+    const DB_USER: &str = "tenant_base_alex";
+
+    fn configuration() -> Configuration {
+        let mut configuration = get_configuration().expect("Failed to read configuration.");
+        configuration.secrets.keyring.active_key_id = "2026-10".to_string();
+        configuration.secrets.keyring.keys =
+            [("2026-04", PREVIOUS_KEY_HEX), ("2026-10", ACTIVE_KEY_HEX)]
+                .into_iter()
+                .map(|(key_id, key)| (key_id.to_string(), SecretString::from(key)))
+                .collect();
+        configuration.secrets.accept_unbound_ciphertexts = true;
+        configuration
+            .secrets
+            .store
+            .replace(Secrets::try_from(&configuration.secrets).unwrap());
+
+        configuration
+    }
+
+    fn cipher(key_hex: &str) -> AesGcmCipher {
+        AesGcmCipher::from_hex(&SecretString::from(key_hex)).unwrap()
+    }
+
+    fn unversioned_cipher(configuration: &Configuration) -> AesGcmCipher {
+        AesGcmCipher::from_hex(&configuration.secrets.aes256_gcm_key).unwrap()
+    }
+
+    #[test]
+    fn test_tenant_password_encryption() {
+        let configuration = configuration();
+        let tenant_id = Uuid::new_v4();
+        let password = generate_password();
+
+        let encrypted =
+            encrypt_tenant_password(&password, &tenant_id, DB_USER, &configuration).unwrap();
+        assert!(encrypted.starts_with(&format!("v3:aes256gcm:2026-10:{}:", tenant_id)));
+
+        let cases = [
+            encrypted,
+            encrypt_versioned_aes_gcm(
+                "2026-04",
+                &cipher(PREVIOUS_KEY_HEX),
+                &password,
+                &tenant_id.to_string(),
+                &tenant_binding(&tenant_id, DB_USER),
+            )
+            .unwrap(),
+            // Bound to the tenant, without naming it
+            format!(
+                "v2:aes256gcm:2026-04:{}",
+                cipher(PREVIOUS_KEY_HEX)
+                    .encrypt(&password, &tenant_binding(&tenant_id, DB_USER))
+                    .unwrap()
+            ),
+            // Named its key, but wasn't bound to the tenant
+            format!(
+                "v1:aes256gcm:2026-04:{}",
+                cipher(PREVIOUS_KEY_HEX).encrypt(&password, &[]).unwrap()
+            ),
+            // Written before ciphertexts named their key
+            unversioned_cipher(&configuration)
+                .encrypt(&password, &[])
+                .unwrap(),
+        ];
+        for ciphertext in cases {
+            assert_eq!(
+                decrypt_tenant_password(&ciphertext, &tenant_id, DB_USER, &configuration)
+                    .unwrap()
+                    .expose_secret(),
+                password.expose_secret()
+            );
+        }
+
+        let unknown_key = encrypt_versioned_aes_gcm(
+            "2025-01",
+            &cipher(PREVIOUS_KEY_HEX),
+            &password,
+            &tenant_id.to_string(),
+            &tenant_binding(&tenant_id, DB_USER),
+        )
+        .unwrap();
+        assert!(
+            decrypt_tenant_password(&unknown_key, &tenant_id, DB_USER, &configuration).is_err()
+        );
+    }
+
+    #[test]
+    fn test_tenant_password_binding() {
+        let mut configuration = configuration();
+        let tenant_id = Uuid::new_v4();
+        let password = generate_password();
+        let encrypted =
+            encrypt_tenant_password(&password, &tenant_id, DB_USER, &configuration).unwrap();
+        let unbound = unversioned_cipher(&configuration)
+            .encrypt(&password, &[])
+            .unwrap();
+
+        let binding_mismatch = |result: Result<SecretString, AppError>| match result {
+            Err(error @ AppError::CiphertextBindingMismatch { .. }) => {
+                error.status_code() == StatusCode::INTERNAL_SERVER_ERROR
+            }
+            _ => false,
+        };
+
+        let decryption_error = |result: Result<SecretString, AppError>| {
+            matches!(result, Err(AppError::InternalError(_)))
+        };
+
+        // Copied onto another tenant's row
+        assert!(binding_mismatch(decrypt_tenant_password(
+            &encrypted,
+            &Uuid::new_v4(),
+            DB_USER,
+            &configuration
+        )));
+
+        // The right tenant, but another role, a corrupt ciphertext or a replaced key
+        assert!(decryption_error(decrypt_tenant_password(
+            &encrypted,
+            &tenant_id,
+            "tenant_base_stefan",
+            &configuration
+        )));
+        let corrupt = format!(
+            "{}{}",
+            &encrypted[..encrypted.len() - 1],
+            if encrypted.ends_with('0') { '1' } else { '0' }
+        );
+        assert!(decryption_error(decrypt_tenant_password(
+            &corrupt,
+            &tenant_id,
+            DB_USER,
+            &configuration
+        )));
+        let mut replaced_key = self::configuration();
+        replaced_key
+            .secrets
+            .keyring
+            .keys
+            .insert("2026-10".to_string(), SecretString::from(PREVIOUS_KEY_HEX));
+        replaced_key
+            .secrets
+            .store
+            .replace(Secrets::try_from(&replaced_key.secrets).unwrap());
+        assert!(decryption_error(decrypt_tenant_password(
+            &encrypted,
+            &tenant_id,
+            DB_USER,
+            &replaced_key
+        )));
+
+        configuration.secrets.accept_unbound_ciphertexts = false;
+        assert!(binding_mismatch(decrypt_tenant_password(
+            &unbound,
+            &tenant_id,
+            DB_USER,
+            &configuration
+        )));
+        assert!(decrypt_tenant_password(&encrypted, &tenant_id, DB_USER, &configuration).is_ok());
+    }
+
+    #[test]
+    fn test_active_encryption_key() {
+        let mut configuration = configuration();
+        let active_cipher = cipher(ACTIVE_KEY_HEX);
+        let encrypted = active_cipher
+            .encrypt(&SecretString::from("password"), &[])
+            .unwrap();
+        let secrets = Secrets::try_from(&configuration.secrets).unwrap();
+        let (key_id, key) = secrets.active_encryption_key().unwrap();
+        assert_eq!(key_id, "2026-10");
+        assert!(key.decrypt(&encrypted, &[]).is_ok());
+
+        configuration.secrets.keyring.active_key_id = "2025-01".to_string();
+        let secrets = Secrets::try_from(&configuration.secrets).unwrap();
+        assert!(secrets.active_encryption_key().is_err());
+
+        // Without a keyring, `aes256_gcm_key` is the active key
+        configuration.secrets.keyring.active_key_id = String::new();
+        configuration.secrets.keyring.keys.clear();
+        let secrets = Secrets::try_from(&configuration.secrets).unwrap();
+        let (key_id, key) = secrets.active_encryption_key().unwrap();
+        let encrypted = unversioned_cipher(&configuration)
+            .encrypt(&SecretString::from("password"), &[])
+            .unwrap();
+        assert_eq!(key_id, "default");
+        assert!(key.decrypt(&encrypted, &[]).is_ok());
+
+        // Keys that don't decode are rejected with the secrets, without revealing them
+        configuration.secrets.keyring.keys =
+            [("2026-10".to_string(), SecretString::from("a1b2c3"))].into();
+        let error = Secrets::try_from(&configuration.secrets).unwrap_err();
+        assert!(error.starts_with("secrets.keyring.keys.2026-10"));
+        assert!(!error.contains("a1b2c3"));
+    }
+}
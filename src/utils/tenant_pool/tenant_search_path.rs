@@ -0,0 +1,80 @@
+use sqlx::postgres::PgPoolOptions;
+use sqlx::Executor;
+
+// Postgres truncates identifiers to 63 bytes (NAMEDATALEN - 1)
+const MAX_SCHEMA_NAME_LENGTH: usize = 63;
+
+/// Pins every connection of the pool to the tenant's schema, right after it is opened.
+///
+/// Only the tenant's schema is on the `search_path`, so unqualified names never
+/// resolve to the shared tables in `public`.
+pub(crate) fn with_search_path(pool_options: PgPoolOptions, schema: &str) -> PgPoolOptions {
+    let statement = format!("SET search_path TO {}", quote_identifier(schema));
+
+    pool_options.after_connect(move |connection, _| {
+        let statement = statement.clone();
+        Box::pin(async move {
+            connection.execute(statement.as_str()).await?;
+            Ok(())
+        })
+    })
+}
+
+/// Tenant schema names are limited to lowercase letters, digits and underscores,
+/// and may not clash with the schemas Postgres reserves or the shared `public` schema.
+pub fn validate_schema_name(schema: &str) -> Result<(), String> {
+    let valid_characters = schema
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    let valid_start = schema
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_');
+    let reserved =
+        schema == "public" || schema == "information_schema" || schema.starts_with("pg_");
+
+    if !valid_characters || !valid_start || schema.len() > MAX_SCHEMA_NAME_LENGTH || reserved {
+        return Err(format!("{} is not a valid tenant schema name.", schema));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("tenant_alex"), "\"tenant_alex\"");
+        assert_eq!(
+            quote_identifier("tenant\"; DROP DATABASE app; --"),
+            "\"tenant\"\"; DROP DATABASE app; --\""
+        );
+    }
+
+    #[test]
+    fn test_validate_schema_name() {
+        assert!(validate_schema_name("tenant_alex").is_ok());
+        assert!(validate_schema_name("_t1").is_ok());
+
+        for schema in [
+            "",
+            "public",
+            "pg_catalog",
+            "pg_toast",
+            "information_schema",
+            "1tenant",
+            "Tenant",
+            "tenant-alex",
+            "tenant\"; --",
+            &"t".repeat(64),
+        ] {
+            assert!(validate_schema_name(schema).is_err(), "{}", schema);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::models::{AppError, AppState, EvictionReason, TenantStatus, TenantSummary};
+use anyhow::anyhow;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Moves a tenant to another status of its lifecycle, see [`TenantStatus::can_transition_to`].
+///
+/// Once a tenant is no longer active its cached pool is closed right away, requests
+/// in flight fail and the next ones are rejected before any pool is built.
+/// Pools whose build raced with the change are evicted once registered, see
+/// [`ensure_tenant_still_active`](crate::utils::ensure_tenant_still_active).
+#[tracing::instrument(
+    name = "Updating tenant status.",
+    fields(tenant_id = %tenant_id, status = status.as_str()),
+    skip(state, pool)
+)]
+pub async fn update_tenant_status(
+    tenant_id: &Uuid,
+    status: TenantStatus,
+    state: &AppState,
+    pool: &PgPool,
+) -> Result<TenantSummary, AppError> {
+    if status == TenantStatus::Deleted {
+        return Err(AppError::BadRequestError(
+            "Tenants are deleted once their deletion grace period ends".to_string(),
+        ));
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    let current: String = sqlx::query_scalar("SELECT status FROM tenants WHERE id = $1 FOR UPDATE")
+        .bind(tenant_id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(AppError::NotFoundError)?;
+    let current =
+        TenantStatus::try_from(current).map_err(|e| AppError::InternalError(anyhow!(e)))?;
+
+    if current != status && !current.can_transition_to(status) {
+        return Err(AppError::ConflictError(format!(
+            "Tenant can't go from {} to {}",
+            current.as_str(),
+            status.as_str()
+        )));
+    }
+
+    let tenant = sqlx::query_as::<_, TenantSummary>(&format!(
+        r#"
+        UPDATE tenants
+        SET status = $2,
+            status_changed_at = CASE WHEN status = $2 THEN status_changed_at ELSE CURRENT_TIMESTAMP END
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        TenantSummary::COLUMNS
+    ))
+    .bind(tenant_id)
+    .bind(status.as_str())
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    tracing::info!(previous_status = current.as_str(), "Updated tenant status");
+
+    forget_tenant_pools(tenant_id, status, state);
+
+    Ok(tenant)
+}
+
+/// Drops what the application caches about a tenant whose status changed,
+/// closing its pool unless the tenant is active.
+pub(crate) fn forget_tenant_pools(tenant_id: &Uuid, status: TenantStatus, state: &AppState) {
+    // The shared pool looks the status up again on its next transaction
+    if let Some(shared_pool) = &state.shared_pool {
+        shared_pool.forget(tenant_id);
+    }
+
+    if status == TenantStatus::Active {
+        return;
+    }
+
+    if let Some(tenant_pool) = state.pools.get(tenant_id) {
+        state
+            .pools
+            .evict(tenant_id, &tenant_pool, EvictionReason::Deactivated);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::TenantPoolRegistry;
+    use crate::utils::{
+        build_tenant_pool, create_tenant, delete_tenant, ensure_tenant_still_active,
+    };
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use sqlx::postgres::PgPoolOptions;
+
+    // Runs against the development database: `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_pool_built_while_suspending_is_evicted() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let state = AppState {
+            pools: TenantPoolRegistry::new(),
+            shared_pool: None,
+        };
+        let name = format!("suspending_{}", Uuid::new_v4().simple());
+        let tenant_id = create_tenant(&name, &pool, &configuration).await.unwrap();
+
+        // The build read `active`, the suspension commits before the pool is registered
+        let built = build_tenant_pool(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        update_tenant_status(&tenant_id, TenantStatus::Suspended, &state, &pool)
+            .await
+            .unwrap();
+        let tenant_pool = state
+            .pools
+            .get_or_try_init(tenant_id, || async { Ok::<_, AppError>(built) })
+            .await
+            .unwrap();
+
+        let error = ensure_tenant_still_active(&tenant_id, &tenant_pool, &state, &pool)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+        assert!(state.pools.get(&tenant_id).is_none());
+        assert!(tenant_pool.pool.is_closed());
+
+        delete_tenant(&tenant_id, &[TenantStatus::Suspended], &state, &pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use crate::configurations::Configuration;
+use crate::models::AppState;
+use crate::utils::get_pool_for_tenant;
+use actix_web::web;
+use sqlx::PgPool;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// Eagerly creates the pools of the configured `warm_tenants` and of the tenants flagged
+/// as `warm`, unless the shared pool serves them. Failures are logged and skipped, the pool
+/// is then built on first request.
+#[tracing::instrument(name = "Warming up tenant pools", skip_all)]
+pub async fn warm_up_tenant_pools(
+    state: web::Data<AppState>,
+    pool: PgPool,
+    configuration: Configuration,
+) {
+    let mut tenant_ids: BTreeSet<Uuid> = configuration
+        .tenant_pools
+        .warm_tenants
+        .iter()
+        .copied()
+        .collect();
+
+    match sqlx::query_scalar::<_, Uuid>("SELECT id FROM tenants WHERE warm AND status = 'active'")
+        .fetch_all(&pool)
+        .await
+    {
+        Ok(flagged_tenant_ids) => tenant_ids.extend(flagged_tenant_ids),
+        Err(e) => tracing::warn!("Failed to load warm tenants: {}", e),
+    }
+
+    // Tenants of the shared database are served by the shared pool, already connected
+    if state.shared_pool.is_some() {
+        match sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM tenants WHERE id = ANY($1) AND isolation_mode <> 'shared'",
+        )
+        .bind(tenant_ids.iter().copied().collect::<Vec<_>>())
+        .fetch_all(&pool)
+        .await
+        {
+            Ok(isolated_tenant_ids) => tenant_ids = isolated_tenant_ids.into_iter().collect(),
+            Err(e) => {
+                tracing::warn!("Failed to load the isolation of warm tenants: {}", e);
+                return;
+            }
+        }
+    }
+
+    for tenant_id in tenant_ids {
+        match get_pool_for_tenant(&tenant_id, &state, &pool, &configuration).await {
+            Ok(_) => tracing::info!(tenant_id = %tenant_id, "Warmed up tenant pool"),
+            Err(e) => tracing::warn!(
+                tenant_id = %tenant_id,
+                error = %e,
+                "Failed to warm up tenant pool"
+            ),
+        }
+    }
+}
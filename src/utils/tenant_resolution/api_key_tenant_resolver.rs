@@ -0,0 +1,44 @@
+use crate::models::{AppError, AuthenticatedApiKey};
+use crate::utils::{TenantKey, TenantResolver};
+use actix_web::{HttpMessage, HttpRequest};
+
+/// Tenant of the API key verified by `ApiKeyAuthentication` for the current request.
+pub struct ApiKeyTenantResolver;
+
+impl TenantResolver for ApiKeyTenantResolver {
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError> {
+        Ok(req
+            .extensions()
+            .get::<AuthenticatedApiKey>()
+            .map(|authenticated| TenantKey::Id(authenticated.api_key.tenant_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ApiKey;
+    use actix_web::test::TestRequest;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_resolve_api_key() {
+        let tenant_id = Uuid::new_v4();
+        let req = TestRequest::default().to_http_request();
+
+        assert_eq!(ApiKeyTenantResolver.resolve(&req).unwrap(), None);
+
+        req.extensions_mut().insert(AuthenticatedApiKey {
+            api_key: ApiKey {
+                tenant_id,
+                ..ApiKey::default()
+            },
+            tenant_name: "acme".to_string(),
+        });
+
+        assert_eq!(
+            ApiKeyTenantResolver.resolve(&req).unwrap(),
+            Some(TenantKey::Id(tenant_id))
+        );
+    }
+}
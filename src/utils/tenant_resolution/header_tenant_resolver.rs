@@ -0,0 +1,65 @@
+use crate::models::AppError;
+use crate::utils::{TenantKey, TenantResolver};
+use actix_web::HttpRequest;
+
+/// Tenant id or name sent in a header, this trusts the caller to pick its own tenant.
+pub struct HeaderTenantResolver {
+    header_name: String,
+}
+
+impl HeaderTenantResolver {
+    pub fn new(header_name: &str) -> Self {
+        HeaderTenantResolver {
+            header_name: header_name.to_ascii_lowercase(),
+        }
+    }
+}
+
+impl TenantResolver for HeaderTenantResolver {
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError> {
+        match req.headers().get(&self.header_name) {
+            Some(value) => value
+                .to_str()
+                .map(|value| Some(TenantKey::parse(value.trim())))
+                .map_err(|_| {
+                    AppError::BadRequestError(format!("Invalid {} header", self.header_name))
+                }),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_resolve_header() {
+        let resolver = HeaderTenantResolver::new("X-Tenant-Id");
+        let tenant_id = Uuid::new_v4();
+
+        let by_id = TestRequest::default()
+            .insert_header(("x-tenant-id", tenant_id.to_string()))
+            .to_http_request();
+        let by_name = TestRequest::default()
+            .insert_header(("x-tenant-id", "acme"))
+            .to_http_request();
+        let invalid = TestRequest::default()
+            .insert_header(("x-tenant-id", vec![0xFF, 0xFF]))
+            .to_http_request();
+        let missing = TestRequest::default().to_http_request();
+
+        assert_eq!(
+            resolver.resolve(&by_id).unwrap(),
+            Some(TenantKey::Id(tenant_id))
+        );
+        assert_eq!(
+            resolver.resolve(&by_name).unwrap(),
+            Some(TenantKey::Name("acme".to_string()))
+        );
+        assert!(resolver.resolve(&invalid).is_err());
+        assert_eq!(resolver.resolve(&missing).unwrap(), None);
+    }
+}
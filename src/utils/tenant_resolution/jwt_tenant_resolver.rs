@@ -0,0 +1,151 @@
+use crate::configurations::JwtTenantResolutionConfiguration;
+use crate::models::{AppError, API_KEY_PREFIX};
+use crate::utils::{TenantKey, TenantResolver};
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use secrecy::ExposeSecret;
+use serde_json::{Map, Value};
+
+/// Tenant id or name in a claim of an HS256 signed bearer token.
+///
+/// Tokens must carry an `exp` claim, and match the issuer and audience when configured.
+/// A request with an invalid token, or a valid token without the claim, is rejected rather
+/// than handed to the next strategy. API keys sent as bearer tokens are left alone.
+pub struct JwtTenantResolver {
+    decoding_key: DecodingKey,
+    validation: Validation,
+    claim: String,
+}
+
+impl From<&JwtTenantResolutionConfiguration> for JwtTenantResolver {
+    fn from(configuration: &JwtTenantResolutionConfiguration) -> Self {
+        let mut validation = Validation::new(Algorithm::HS256);
+        if let Some(issuer) = &configuration.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &configuration.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        JwtTenantResolver {
+            decoding_key: DecodingKey::from_secret(configuration.secret.expose_secret().as_bytes()),
+            validation,
+            claim: configuration.claim.clone(),
+        }
+    }
+}
+
+impl TenantResolver for JwtTenantResolver {
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError> {
+        let token = match req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .filter(|token| !token.starts_with(API_KEY_PREFIX))
+        {
+            Some(token) => token.trim(),
+            None => return Ok(None),
+        };
+
+        let claims = decode::<Map<String, Value>>(token, &self.decoding_key, &self.validation)
+            .map_err(|e| AppError::AuthenticationFailed(e.to_string()))?
+            .claims;
+
+        match claims.get(&self.claim) {
+            Some(Value::String(tenant)) => Ok(Some(TenantKey::parse(tenant))),
+            _ => Err(AppError::AuthenticationFailed(format!(
+                "Token has no {} claim",
+                self.claim
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use secrecy::SecretString;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    const SECRET: &str = "test-secret";
+
+    fn resolver() -> JwtTenantResolver {
+        JwtTenantResolver::from(&JwtTenantResolutionConfiguration {
+            secret: SecretString::from(SECRET),
+            claim: "tenant_id".to_string(),
+            issuer: Some("issuer".to_string()),
+            audience: None,
+        })
+    }
+
+    fn request_with_token(claims: Value, secret: &str) -> HttpRequest {
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap();
+
+        TestRequest::default()
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .to_http_request()
+    }
+
+    #[test]
+    fn test_resolve_claim() {
+        let tenant_id = Uuid::new_v4();
+        let exp = chrono::Utc::now().timestamp() + 60;
+        let req = request_with_token(
+            json!({ "tenant_id": tenant_id, "iss": "issuer", "exp": exp }),
+            SECRET,
+        );
+
+        assert_eq!(
+            resolver().resolve(&req).unwrap(),
+            Some(TenantKey::Id(tenant_id))
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_tokens() {
+        let exp = chrono::Utc::now().timestamp() + 60;
+        let cases = [
+            json!({ "tenant_id": "acme", "iss": "issuer", "exp": exp }),
+            json!({ "tenant_id": "acme", "iss": "other", "exp": exp }),
+            json!({ "tenant_id": "acme", "iss": "issuer", "exp": exp - 3600 }),
+            json!({ "tenant_id": "acme", "iss": "issuer" }),
+            json!({ "iss": "issuer", "exp": exp }),
+        ];
+
+        for (i, claims) in cases.into_iter().enumerate() {
+            let secret = if i == 0 { "other-secret" } else { SECRET };
+            let req = request_with_token(claims, secret);
+
+            assert!(
+                matches!(
+                    resolver().resolve(&req),
+                    Err(AppError::AuthenticationFailed(_))
+                ),
+                "Case {} should have been rejected",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_without_bearer_token() {
+        let req = TestRequest::default().to_http_request();
+        let with_api_key = TestRequest::default()
+            .insert_header((header::AUTHORIZATION, "Bearer tk_prefix_secret"))
+            .to_http_request();
+
+        assert_eq!(resolver().resolve(&req).unwrap(), None);
+        assert_eq!(resolver().resolve(&with_api_key).unwrap(), None);
+    }
+}
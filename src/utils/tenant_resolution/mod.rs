@@ -0,0 +1,19 @@
+mod api_key_tenant_resolver;
+mod header_tenant_resolver;
+mod jwt_tenant_resolver;
+mod path_tenant_resolver;
+mod session_tenant_resolver;
+mod subdomain_tenant_resolver;
+mod tenant_context;
+mod tenant_name_cache;
+mod tenant_resolver;
+
+pub use api_key_tenant_resolver::*;
+pub use header_tenant_resolver::*;
+pub use jwt_tenant_resolver::*;
+pub use path_tenant_resolver::*;
+pub use session_tenant_resolver::*;
+pub use subdomain_tenant_resolver::*;
+pub use tenant_context::*;
+pub use tenant_name_cache::*;
+pub use tenant_resolver::*;
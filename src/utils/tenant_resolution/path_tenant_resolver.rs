@@ -0,0 +1,62 @@
+use crate::models::AppError;
+use crate::utils::{TenantKey, TenantResolver};
+use actix_web::HttpRequest;
+
+/// Tenant id or name as the first path segment after the prefix, e.g. `/t/acme/v1/products`.
+pub struct PathTenantResolver {
+    prefix: String,
+}
+
+impl PathTenantResolver {
+    pub fn new(prefix: &str) -> Self {
+        PathTenantResolver {
+            prefix: format!("/{}/", prefix.trim_matches('/')),
+        }
+    }
+
+    /// Scope the routes are mounted under, e.g. `/t/{tenant}`.
+    pub fn scope_path(&self) -> String {
+        format!("{}{{tenant}}", self.prefix)
+    }
+}
+
+impl TenantResolver for PathTenantResolver {
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError> {
+        Ok(req
+            .path()
+            .strip_prefix(&self.prefix)
+            .and_then(|rest| rest.split('/').next())
+            .filter(|segment| !segment.is_empty())
+            .map(TenantKey::parse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_resolve_path() {
+        let resolver = PathTenantResolver::new("/t");
+        let cases = [
+            ("/t/acme/v1/products", Some("acme")),
+            ("/t/acme", Some("acme")),
+            ("/t/", None),
+            ("/v1/products", None),
+            ("/tenants/acme", None),
+        ];
+
+        for (path, expected) in cases {
+            let req = TestRequest::with_uri(path).to_http_request();
+
+            assert_eq!(
+                resolver.resolve(&req).unwrap(),
+                expected.map(|name| TenantKey::Name(name.to_string())),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(resolver.scope_path(), "/t/{tenant}");
+    }
+}
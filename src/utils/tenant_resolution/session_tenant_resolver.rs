@@ -0,0 +1,26 @@
+use crate::models::AppError;
+use crate::utils::{TenantKey, TenantResolver};
+use actix_session::SessionExt;
+use actix_web::HttpRequest;
+
+/// Tenant id or name stored in the actix session, e.g. at login.
+pub struct SessionTenantResolver {
+    key: String,
+}
+
+impl SessionTenantResolver {
+    pub fn new(key: &str) -> Self {
+        SessionTenantResolver {
+            key: key.to_string(),
+        }
+    }
+}
+
+impl TenantResolver for SessionTenantResolver {
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError> {
+        req.get_session()
+            .get::<String>(&self.key)
+            .map(|value| value.as_deref().map(TenantKey::parse))
+            .map_err(|_| AppError::BadRequestError("Invalid tenant in session".to_string()))
+    }
+}
@@ -0,0 +1,91 @@
+use crate::models::AppError;
+use crate::utils::{TenantKey, TenantResolver};
+use actix_web::http::header;
+use actix_web::HttpRequest;
+
+/// Tenant name as the leftmost label of the host, `acme-corp.example.com` is the tenant `acme_corp`.
+///
+/// Only the `Host` header, or the authority of HTTP/2 requests, is read. Forwarding headers
+/// are ignored since any client can set them.
+pub struct SubdomainTenantResolver {
+    // Kept with its leading dot, e.g. `.example.com`
+    base_domain_suffix: String,
+}
+
+impl SubdomainTenantResolver {
+    pub fn new(base_domain: &str) -> Self {
+        SubdomainTenantResolver {
+            base_domain_suffix: format!(".{}", base_domain.trim_matches('.').to_ascii_lowercase()),
+        }
+    }
+}
+
+impl TenantResolver for SubdomainTenantResolver {
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError> {
+        let host = match req.uri().host() {
+            Some(host) => host,
+            None => match req.headers().get(header::HOST).map(|host| host.to_str()) {
+                Some(Ok(host)) => host,
+                Some(Err(_)) => {
+                    return Err(AppError::BadRequestError("Invalid host header".to_string()))
+                }
+                None => return Ok(None),
+            },
+        };
+        let host = host
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        Ok(host
+            .strip_suffix(&self.base_domain_suffix)
+            // Only direct subdomains, `www.acme.example.com` doesn't name a tenant
+            .filter(|subdomain| !subdomain.is_empty() && !subdomain.contains('.'))
+            .map(TenantKey::parse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_resolve_subdomain() {
+        let resolver = SubdomainTenantResolver::new("example.com");
+        let cases = [
+            ("acme.example.com", Some("acme")),
+            ("ACME.Example.com:8443", Some("acme")),
+            ("acme-corp.example.com", Some("acme_corp")),
+            ("example.com", None),
+            ("www.acme.example.com", None),
+            ("acme.example.org", None),
+            ("acmeexample.com", None),
+        ];
+
+        for (host, expected) in cases {
+            let req = TestRequest::default()
+                .insert_header((header::HOST, host))
+                .to_http_request();
+
+            assert_eq!(
+                resolver.resolve(&req).unwrap(),
+                expected.map(|name| TenantKey::Name(name.to_string())),
+                "{}",
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn test_ignores_forwarded_host() {
+        let resolver = SubdomainTenantResolver::new("example.com");
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com"))
+            .insert_header(("x-forwarded-host", "acme.example.com"))
+            .to_http_request();
+
+        assert_eq!(resolver.resolve(&req).unwrap(), None);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, AppState, AuthenticatedApiKey, TenantTransactionSettings};
+use crate::utils::{begin_tenant_transaction, begin_tenant_transaction_with, TenantResolverChain};
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use anyhow::anyhow;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
+
+/// The tenant a request acts for, resolved by the configured [`TenantResolverChain`].
+///
+/// Taken as a handler argument it replaces resolving the tenant and passing the application
+/// state, pool and configuration around. Unknown tenants are rejected with a `404` while
+/// extracting, the tenant's connection is only taken when the handler starts a transaction. Requests
+/// authenticated with an API key only act for the key's tenant, whichever strategy resolved
+/// it, others are rejected with a `403`.
+#[derive(Clone)]
+pub struct TenantContext {
+    id: Uuid,
+    name: String,
+    state: web::Data<AppState>,
+    pool: web::Data<PgPool>,
+    configuration: web::Data<Configuration>,
+}
+
+impl TenantContext {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Starts a transaction acting as the tenant, whichever pool strategy is configured,
+    /// see [`begin_tenant_transaction`].
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>, AppError> {
+        begin_tenant_transaction(&self.id, &self.state, &self.pool, &self.configuration).await
+    }
+
+    pub async fn begin_with(
+        &self,
+        settings: &TenantTransactionSettings,
+    ) -> Result<Transaction<'static, Postgres>, AppError> {
+        begin_tenant_transaction_with(
+            &self.id,
+            &self.state,
+            &self.pool,
+            &self.configuration,
+            settings,
+        )
+        .await
+    }
+
+    async fn from_http_request(req: &HttpRequest) -> Result<Self, AppError> {
+        let tenant_resolver = app_data::<TenantResolverChain>(req)?;
+        let state = app_data::<AppState>(req)?;
+        let pool = app_data::<PgPool>(req)?;
+        let configuration = app_data::<Configuration>(req)?;
+
+        let id = tenant_resolver.resolve(req, &pool).await?;
+        ensure_api_key_tenant_matches(req, &id)?;
+        let name = tenant_resolver
+            .tenant_names()
+            .get_name(&id, &pool)
+            .await?
+            .ok_or(AppError::NotFoundError)?;
+
+        Ok(TenantContext {
+            id,
+            name,
+            state,
+            pool,
+            configuration,
+        })
+    }
+}
+
+impl FromRequest for TenantContext {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move { TenantContext::from_http_request(&req).await })
+    }
+}
+
+/// Strategies tried before `api_key` may pick another tenant than the key's from the path,
+/// subdomain or a token.
+fn ensure_api_key_tenant_matches(req: &HttpRequest, tenant_id: &Uuid) -> Result<(), AppError> {
+    match req.extensions().get::<AuthenticatedApiKey>() {
+        Some(authenticated) if authenticated.api_key.tenant_id != *tenant_id => Err(
+            AppError::AuthorizationFailed("The API key belongs to another tenant".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn app_data<T: 'static>(req: &HttpRequest) -> Result<web::Data<T>, AppError> {
+    req.app_data::<web::Data<T>>().cloned().ok_or_else(|| {
+        AppError::InternalError(anyhow!(
+            "{} is missing from the application data",
+            std::any::type_name::<T>()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::{ApiKey, TenantPoolRegistry};
+    use crate::utils::{HeaderTenantResolver, TenantNameCache};
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::ResponseError;
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+
+    fn request(pool: PgPool) -> TestRequest {
+        TestRequest::default()
+            .app_data(web::Data::new(
+                TenantResolverChain::new(TenantNameCache::new(Duration::from_secs(60)))
+                    .with_resolver(HeaderTenantResolver::new("x-tenant-id")),
+            ))
+            .app_data(web::Data::new(AppState {
+                pools: TenantPoolRegistry::new(),
+                shared_pool: None,
+            }))
+            .app_data(web::Data::new(pool))
+            .app_data(web::Data::new(
+                get_configuration().expect("Failed to read configuration."),
+            ))
+    }
+
+    #[actix_web::test]
+    async fn test_extract_without_tenant() {
+        // Never connects, the request is rejected before any lookup
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let (req, mut payload) = request(pool).to_http_parts();
+
+        let error = TenantContext::from_request(&req, &mut payload)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_extract_with_api_key_of_another_tenant() {
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let tenant_id = Uuid::new_v4();
+        let (req, mut payload) = request(pool)
+            .insert_header(("x-tenant-id", tenant_id.to_string()))
+            .to_http_parts();
+        req.extensions_mut().insert(AuthenticatedApiKey {
+            api_key: ApiKey {
+                tenant_id: Uuid::new_v4(),
+                ..ApiKey::default()
+            },
+            tenant_name: "acme".to_string(),
+        });
+
+        // Rejected before the tenant's name is looked up
+        let error = TenantContext::from_request(&req, &mut payload)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn test_extract_without_resolver() {
+        let (req, mut payload) = TestRequest::default()
+            .insert_header(("x-tenant-id", Uuid::new_v4().to_string()))
+            .to_http_parts();
+
+        let error = TenantContext::from_request(&req, &mut payload)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Runs against the development database: `cargo test -- --ignored`
+    #[actix_web::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_extract_tenant() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let (tenant_id, name): (Uuid, String) = sqlx::query_as(
+            "SELECT id, name FROM tenants WHERE status = 'active' ORDER BY created_at LIMIT 1",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Expected at least one active tenant.");
+
+        let (by_name, mut payload) = request(pool.clone())
+            .insert_header(("x-tenant-id", name.as_str()))
+            .to_http_parts();
+        let tenant = TenantContext::from_request(&by_name, &mut payload)
+            .await
+            .unwrap();
+        let mut transaction = tenant.begin().await.unwrap();
+        let current_user: String = sqlx::query_scalar("SELECT current_user::text")
+            .fetch_one(&mut *transaction)
+            .await
+            .unwrap();
+
+        assert_eq!(tenant.id(), tenant_id);
+        assert_eq!(tenant.name(), name);
+        assert!(current_user.ends_with(&name));
+
+        let (unknown, mut payload) = request(pool)
+            .insert_header(("x-tenant-id", Uuid::new_v4().to_string()))
+            .to_http_parts();
+        let error = TenantContext::from_request(&unknown, &mut payload)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+    }
+}
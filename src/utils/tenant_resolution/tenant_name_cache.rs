@@ -0,0 +1,130 @@
+use crate::models::AppError;
+use crate::utils::validate_tenant_name;
+use dashmap::DashMap;
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Tenant ids by name and names by id, so that neither is queried from `tenants` on every request.
+///
+/// Only existing tenants are cached, which keeps the cache bounded by the number of tenants.
+/// Entries are refreshed once older than the ttl, a tenant being renamed or deleted is
+/// noticed by then at the latest.
+pub struct TenantNameCache {
+    ttl: Duration,
+    entries: DashMap<String, (Uuid, Instant)>,
+    names: DashMap<Uuid, (String, Instant)>,
+}
+
+impl TenantNameCache {
+    pub fn new(ttl: Duration) -> Self {
+        TenantNameCache {
+            ttl,
+            entries: DashMap::new(),
+            names: DashMap::new(),
+        }
+    }
+
+    pub async fn get_name(
+        &self,
+        tenant_id: &Uuid,
+        pool: &PgPool,
+    ) -> Result<Option<String>, AppError> {
+        if let Some(entry) = self.names.get(tenant_id) {
+            let (name, cached_at) = &*entry;
+            if cached_at.elapsed() < self.ttl {
+                return Ok(Some(name.clone()));
+            }
+        }
+
+        let name: Option<String> = sqlx::query_scalar("SELECT name FROM tenants WHERE id = $1")
+            .bind(tenant_id)
+            .fetch_optional(pool)
+            .await?;
+
+        match &name {
+            Some(name) => {
+                self.names
+                    .insert(*tenant_id, (name.clone(), Instant::now()));
+            }
+            None => {
+                self.names.remove(tenant_id);
+            }
+        }
+
+        Ok(name)
+    }
+
+    pub async fn get_id(&self, name: &str, pool: &PgPool) -> Result<Option<Uuid>, AppError> {
+        if let Some(entry) = self.entries.get(name) {
+            let (tenant_id, cached_at) = *entry;
+            if cached_at.elapsed() < self.ttl {
+                return Ok(Some(tenant_id));
+            }
+        }
+
+        // Names that can't exist never reach the database
+        if validate_tenant_name(name).is_err() {
+            return Ok(None);
+        }
+
+        let tenant_id: Option<Uuid> = sqlx::query_scalar("SELECT id FROM tenants WHERE name = $1")
+            .bind(name)
+            .fetch_optional(pool)
+            .await?;
+
+        match tenant_id {
+            Some(tenant_id) => {
+                self.entries
+                    .insert(name.to_string(), (tenant_id, Instant::now()));
+            }
+            None => {
+                self.entries.remove(name);
+            }
+        }
+
+        Ok(tenant_id)
+    }
+
+    pub fn forget(&self, tenant_id: &Uuid) {
+        self.entries
+            .retain(|_, (cached_id, _)| cached_id != tenant_id);
+        self.names.remove(tenant_id);
+    }
+}
+
+// These run against the development database: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use sqlx::postgres::PgPoolOptions;
+
+    #[tokio::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_get_id_caches_existing_tenants() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let (tenant_id, name): (Uuid, String) =
+            sqlx::query_as("SELECT id, name FROM tenants ORDER BY created_at LIMIT 1")
+                .fetch_one(&pool)
+                .await
+                .expect("Expected at least one tenant.");
+        let cache = TenantNameCache::new(Duration::from_secs(60));
+
+        assert_eq!(cache.get_id(&name, &pool).await.unwrap(), Some(tenant_id));
+        assert_eq!(
+            cache.get_name(&tenant_id, &pool).await.unwrap(),
+            Some(name.clone())
+        );
+        assert_eq!(cache.get_name(&Uuid::new_v4(), &pool).await.unwrap(), None);
+        // Served from the cache once the pool is gone
+        pool.close().await;
+        assert_eq!(cache.get_id(&name, &pool).await.unwrap(), Some(tenant_id));
+        assert_eq!(cache.get_name(&tenant_id, &pool).await.unwrap(), Some(name));
+        assert_eq!(cache.get_id("Not a tenant", &pool).await.unwrap(), None);
+    }
+}
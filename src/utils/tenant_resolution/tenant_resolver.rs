@@ -0,0 +1,173 @@
+use crate::configurations::{TenantResolutionConfiguration, TenantResolutionStrategy};
+use crate::models::AppError;
+use crate::utils::{
+    ApiKeyTenantResolver, HeaderTenantResolver, JwtTenantResolver, PathTenantResolver,
+    SessionTenantResolver, SubdomainTenantResolver, TenantNameCache,
+};
+use actix_web::HttpRequest;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How a request refers to its tenant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TenantKey {
+    Id(Uuid),
+    Name(String),
+}
+
+impl TenantKey {
+    /// Values that aren't a UUID are taken as a tenant name or its slug, where `-` stands for `_`.
+    pub fn parse(value: &str) -> Self {
+        match Uuid::parse_str(value) {
+            Ok(id) => TenantKey::Id(id),
+            Err(_) => TenantKey::Name(value.to_ascii_lowercase().replace('-', "_")),
+        }
+    }
+}
+
+pub trait TenantResolver: Send + Sync {
+    /// Returns `Ok(None)` when the request carries nothing this strategy looks at,
+    /// the next strategy of the chain is tried then.
+    fn resolve(&self, req: &HttpRequest) -> Result<Option<TenantKey>, AppError>;
+}
+
+/// Strategies tried in order until one of them finds the tenant of a request,
+/// see `TenantResolutionConfiguration`.
+pub struct TenantResolverChain {
+    resolvers: Vec<Box<dyn TenantResolver>>,
+    tenant_names: TenantNameCache,
+}
+
+impl TenantResolverChain {
+    pub fn new(tenant_names: TenantNameCache) -> Self {
+        TenantResolverChain {
+            resolvers: Vec::new(),
+            tenant_names,
+        }
+    }
+
+    pub fn with_resolver(mut self, resolver: impl TenantResolver + 'static) -> Self {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+
+    pub fn from_configuration(
+        configuration: &TenantResolutionConfiguration,
+    ) -> Result<Self, String> {
+        let tenant_names =
+            TenantNameCache::new(Duration::from_secs(configuration.name_cache_ttl_in_seconds));
+
+        configuration.strategies.iter().try_fold(
+            TenantResolverChain::new(tenant_names),
+            |chain, strategy| {
+                Ok(match strategy {
+                    TenantResolutionStrategy::ApiKey => chain.with_resolver(ApiKeyTenantResolver),
+                    TenantResolutionStrategy::Header => {
+                        chain.with_resolver(HeaderTenantResolver::new(&configuration.header_name))
+                    }
+                    TenantResolutionStrategy::Subdomain => {
+                        let base_domain = configuration.base_domain.as_deref().ok_or(
+                            "`tenant_resolution.base_domain` is required by the subdomain strategy",
+                        )?;
+                        chain.with_resolver(SubdomainTenantResolver::new(base_domain))
+                    }
+                    TenantResolutionStrategy::Path => {
+                        chain.with_resolver(PathTenantResolver::new(&configuration.path_prefix))
+                    }
+                    TenantResolutionStrategy::Jwt => {
+                        let jwt_configuration = configuration
+                            .jwt
+                            .as_ref()
+                            .ok_or("`tenant_resolution.jwt` is required by the jwt strategy")?;
+                        chain.with_resolver(JwtTenantResolver::from(jwt_configuration))
+                    }
+                    TenantResolutionStrategy::Session => {
+                        chain.with_resolver(SessionTenantResolver::new(&configuration.session_key))
+                    }
+                })
+            },
+        )
+    }
+
+    /// Tenant names are looked up in `tenants`, unknown names are reported as not found.
+    pub async fn resolve(&self, req: &HttpRequest, pool: &PgPool) -> Result<Uuid, AppError> {
+        for resolver in &self.resolvers {
+            match resolver.resolve(req)? {
+                Some(TenantKey::Id(tenant_id)) => return Ok(tenant_id),
+                Some(TenantKey::Name(name)) => {
+                    return self
+                        .tenant_names
+                        .get_id(&name, pool)
+                        .await?
+                        .ok_or(AppError::NotFoundError)
+                }
+                None => continue,
+            }
+        }
+
+        Err(AppError::BadRequestError(
+            "No tenant found in the request".to_string(),
+        ))
+    }
+
+    pub fn tenant_names(&self) -> &TenantNameCache {
+        &self.tenant_names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use sqlx::postgres::PgPoolOptions;
+
+    #[test]
+    fn test_tenant_key_parse() {
+        let tenant_id = Uuid::new_v4();
+
+        assert_eq!(
+            TenantKey::parse(&tenant_id.to_string()),
+            TenantKey::Id(tenant_id)
+        );
+        assert_eq!(
+            TenantKey::parse("acme"),
+            TenantKey::Name("acme".to_string())
+        );
+        assert_eq!(
+            TenantKey::parse("Acme-Corp"),
+            TenantKey::Name("acme_corp".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chain_uses_first_matching_strategy() {
+        let tenant_id = Uuid::new_v4();
+        let other_tenant_id = Uuid::new_v4();
+        // Never connects, ids are resolved without touching the database
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let chain = TenantResolverChain::new(TenantNameCache::new(Duration::from_secs(60)))
+            .with_resolver(PathTenantResolver::new("/t"))
+            .with_resolver(HeaderTenantResolver::new("x-tenant-id"));
+
+        let from_path = TestRequest::with_uri(&format!("/t/{}/v1/products", tenant_id))
+            .insert_header(("x-tenant-id", other_tenant_id.to_string()))
+            .to_http_request();
+        let from_header = TestRequest::with_uri("/v1/products")
+            .insert_header(("x-tenant-id", other_tenant_id.to_string()))
+            .to_http_request();
+        let without_tenant = TestRequest::with_uri("/v1/products").to_http_request();
+
+        assert_eq!(chain.resolve(&from_path, &pool).await.unwrap(), tenant_id);
+        assert_eq!(
+            chain.resolve(&from_header, &pool).await.unwrap(),
+            other_tenant_id
+        );
+        assert!(matches!(
+            chain.resolve(&without_tenant, &pool).await,
+            Err(AppError::BadRequestError(_))
+        ));
+    }
+}
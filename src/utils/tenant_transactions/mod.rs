@@ -0,0 +1,5 @@
+mod tenant_transaction;
+mod tenant_transaction_scope;
+
+pub use tenant_transaction::*;
+pub use tenant_transaction_scope::*;
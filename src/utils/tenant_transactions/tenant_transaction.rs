@@ -0,0 +1,105 @@
+use crate::models::AppError;
+use crate::utils::{TenantContext, TenantTransactionSlot};
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use anyhow::anyhow;
+use sqlx::PgConnection;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use tokio::sync::OwnedMutexGuard;
+
+/// The transaction of the current request, opened on the tenant's pool when first extracted.
+///
+/// Only available on routes wrapped in a [`TenantTransactionScope`](crate::utils::TenantTransactionScope),
+/// which commits or rolls it back once the handler is done. Handlers never commit it themselves.
+pub struct TenantTransaction {
+    tenant: TenantContext,
+    slot: TenantTransactionSlot,
+}
+
+impl TenantTransaction {
+    pub fn tenant(&self) -> &TenantContext {
+        &self.tenant
+    }
+
+    /// Connection to run the handler's queries on, e.g. `.fetch_one(&mut *transaction.lock().await)`.
+    pub async fn lock(&self) -> TenantTransactionGuard {
+        TenantTransactionGuard(self.slot.transaction.clone().lock_owned().await)
+    }
+
+    async fn from_http_request(req: &HttpRequest) -> Result<Self, AppError> {
+        let slot = req
+            .extensions()
+            .get::<TenantTransactionSlot>()
+            .cloned()
+            .ok_or_else(|| {
+                AppError::InternalError(anyhow!(
+                    "TenantTransaction requires the route to be wrapped in a TenantTransactionScope"
+                ))
+            })?;
+        let tenant = TenantContext::extract(req).await?;
+
+        {
+            let mut transaction = slot.transaction.lock().await;
+            if transaction.is_none() {
+                *transaction = Some(tenant.begin_with(&slot.settings).await?);
+            }
+        }
+
+        Ok(TenantTransaction { tenant, slot })
+    }
+}
+
+impl FromRequest for TenantTransaction {
+    type Error = AppError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move { TenantTransaction::from_http_request(&req).await })
+    }
+}
+
+pub struct TenantTransactionGuard(
+    OwnedMutexGuard<Option<sqlx::Transaction<'static, sqlx::Postgres>>>,
+);
+
+impl Deref for TenantTransactionGuard {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+            .as_deref()
+            .expect("The tenant transaction is open until the handler returns")
+    }
+}
+
+impl DerefMut for TenantTransactionGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+            .as_deref_mut()
+            .expect("The tenant transaction is open until the handler returns")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::ResponseError;
+
+    #[actix_web::test]
+    async fn test_extract_outside_scope() {
+        let (req, mut payload) = TestRequest::default().to_http_parts();
+
+        let error = TenantTransaction::from_request(&req, &mut payload)
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::models::{AppError, IsolationLevel, TenantTransactionSettings};
+use actix_web::body::EitherBody;
+use actix_web::dev::{self, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage, ResponseError};
+use anyhow::anyhow;
+use sqlx::{Postgres, Transaction};
+use std::future::{poll_fn, ready, Future, Ready};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Middleware giving each request a single tenant transaction, see [`TenantTransaction`](crate::utils::TenantTransaction).
+///
+/// The transaction is committed when the handler returns a `2xx` response and rolled back on
+/// any other response, an error or a panic. A failed commit turns the response into a `500`.
+/// Wrap routes individually to give them their own isolation level or statement timeout.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TenantTransactionScope {
+    settings: TenantTransactionSettings,
+}
+
+impl TenantTransactionScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_isolation_level(mut self, isolation_level: IsolationLevel) -> Self {
+        self.settings = self.settings.with_isolation_level(isolation_level);
+        self
+    }
+
+    pub fn with_statement_timeout(mut self, statement_timeout: Duration) -> Self {
+        self.settings = self.settings.with_statement_timeout(statement_timeout);
+        self
+    }
+}
+
+/// Shared between the middleware and the extractor through the request extensions.
+#[derive(Clone)]
+pub(crate) struct TenantTransactionSlot {
+    pub(crate) settings: TenantTransactionSettings,
+    pub(crate) transaction: Arc<Mutex<Option<Transaction<'static, Postgres>>>>,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for TenantTransactionScope
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = TenantTransactionScopeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TenantTransactionScopeMiddleware {
+            service: Rc::new(service),
+            settings: self.settings,
+        }))
+    }
+}
+
+pub struct TenantTransactionScopeMiddleware<S> {
+    service: Rc<S>,
+    settings: TenantTransactionSettings,
+}
+
+impl<S, B> Service<ServiceRequest> for TenantTransactionScopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+        let slot = TenantTransactionSlot {
+            settings: self.settings,
+            transaction: Arc::new(Mutex::new(None)),
+        };
+        req.extensions_mut().insert(slot.clone());
+        // Kept to answer the request should the handler panic
+        let http_request = req.request().clone();
+
+        Box::pin(async move {
+            let mut response = pin!(srv.call(req));
+            let result =
+                poll_fn(
+                    |cx| match catch_unwind(AssertUnwindSafe(|| response.as_mut().poll(cx))) {
+                        Ok(Poll::Ready(result)) => Poll::Ready(Some(result)),
+                        Ok(Poll::Pending) => Poll::Pending,
+                        Err(_) => Poll::Ready(None),
+                    },
+                )
+                .await;
+            let transaction = slot.transaction.lock().await.take();
+
+            match result {
+                Some(Ok(response)) => {
+                    let Some(transaction) = transaction else {
+                        return Ok(response.map_into_left_body());
+                    };
+
+                    if !response.status().is_success() {
+                        rollback(transaction).await;
+                        return Ok(response.map_into_left_body());
+                    }
+
+                    match transaction.commit().await {
+                        Ok(()) => Ok(response.map_into_left_body()),
+                        Err(e) => {
+                            tracing::error!(error = %e, "Failed to commit tenant transaction");
+                            let (request, _) = response.into_parts();
+                            Ok(ServiceResponse::new(
+                                request,
+                                AppError::DatabaseError(e).error_response(),
+                            )
+                            .map_into_right_body())
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    if let Some(transaction) = transaction {
+                        rollback(transaction).await;
+                    }
+                    Err(e)
+                }
+                None => {
+                    tracing::error!("Request handler panicked");
+                    if let Some(transaction) = transaction {
+                        rollback(transaction).await;
+                    }
+                    Ok(ServiceResponse::new(
+                        http_request,
+                        AppError::InternalError(anyhow!("Request handler panicked"))
+                            .error_response(),
+                    )
+                    .map_into_right_body())
+                }
+            }
+        })
+    }
+}
+
+async fn rollback(transaction: Transaction<'static, Postgres>) {
+    // Dropping the transaction would roll it back too, but only once its connection is reused
+    if let Err(e) = transaction.rollback().await {
+        tracing::warn!(error = %e, "Failed to roll back tenant transaction");
+    }
+}
+
+// These run against the development database: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::{AppState, TenantPoolRegistry};
+    use crate::utils::{
+        HeaderTenantResolver, TenantNameCache, TenantResolverChain, TenantTransaction,
+    };
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpResponse};
+    use sqlx::postgres::PgPoolOptions;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    async fn insert_user(transaction: &TenantTransaction, first_name: &str) {
+        sqlx::query("INSERT INTO users (first_name, last_name) VALUES ($1, 'Scope')")
+            .bind(first_name)
+            .execute(&mut *transaction.lock().await)
+            .await
+            .unwrap();
+    }
+
+    async fn commit(transaction: TenantTransaction, path: web::Path<String>) -> HttpResponse {
+        insert_user(&transaction, &path).await;
+        HttpResponse::Created().finish()
+    }
+
+    async fn fail(
+        transaction: TenantTransaction,
+        path: web::Path<String>,
+    ) -> Result<HttpResponse, AppError> {
+        insert_user(&transaction, &path).await;
+        Err(AppError::BadRequestError("Rejected".to_string()))
+    }
+
+    async fn panic(transaction: TenantTransaction, path: web::Path<String>) -> HttpResponse {
+        insert_user(&transaction, &path).await;
+        panic!("Handler failed after writing");
+    }
+
+    async fn settings(transaction: TenantTransaction) -> Result<HttpResponse, AppError> {
+        let (isolation_level, statement_timeout): (String, String) = sqlx::query_as(
+            "SELECT current_setting('transaction_isolation'), current_setting('statement_timeout')",
+        )
+        .fetch_one(&mut *transaction.lock().await)
+        .await?;
+
+        Ok(HttpResponse::Ok().body(format!("{} {}", isolation_level, statement_timeout)))
+    }
+
+    async fn users_named(pool: &PgPool, first_name: &str) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE first_name = $1")
+            .bind(first_name)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_commits_only_successful_requests() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let tenant_name: String = sqlx::query_scalar(
+            "SELECT name FROM tenants WHERE status = 'active' AND isolation_mode = 'shared' ORDER BY created_at LIMIT 1",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Expected at least one active shared tenant.");
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(
+                    TenantResolverChain::new(TenantNameCache::new(Duration::from_secs(60)))
+                        .with_resolver(HeaderTenantResolver::new("x-tenant-id")),
+                ))
+                .app_data(web::Data::new(AppState {
+                    pools: TenantPoolRegistry::new(),
+                    shared_pool: None,
+                }))
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(configuration))
+                .route(
+                    "/commit/{name}",
+                    web::post().to(commit).wrap(TenantTransactionScope::new()),
+                )
+                .route(
+                    "/fail/{name}",
+                    web::post().to(fail).wrap(TenantTransactionScope::new()),
+                )
+                .route(
+                    "/panic/{name}",
+                    web::post().to(panic).wrap(TenantTransactionScope::new()),
+                )
+                .route(
+                    "/settings",
+                    web::get().to(settings).wrap(
+                        TenantTransactionScope::new()
+                            .with_isolation_level(IsolationLevel::Serializable)
+                            .with_statement_timeout(Duration::from_millis(1500)),
+                    ),
+                ),
+        )
+        .await;
+
+        let mut statuses = Vec::new();
+        let mut names = Vec::new();
+        for path in ["commit", "fail", "panic"] {
+            let name = format!("scope_{}_{}", path, Uuid::new_v4().simple());
+            let req = test::TestRequest::post()
+                .uri(&format!("/{}/{}", path, name))
+                .insert_header(("x-tenant-id", tenant_name.as_str()))
+                .to_request();
+            statuses.push(test::call_service(&app, req).await.status());
+            names.push(name);
+        }
+        let req = test::TestRequest::get()
+            .uri("/settings")
+            .insert_header(("x-tenant-id", tenant_name.as_str()))
+            .to_request();
+        let settings = test::call_and_read_body(&app, req).await;
+
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::CREATED,
+                StatusCode::BAD_REQUEST,
+                StatusCode::INTERNAL_SERVER_ERROR
+            ]
+        );
+        assert_eq!(users_named(&pool, &names[0]).await, 1);
+        assert_eq!(users_named(&pool, &names[1]).await, 0);
+        assert_eq!(users_named(&pool, &names[2]).await, 0);
+        assert_eq!(settings, "serializable 1500ms");
+
+        sqlx::query("DELETE FROM users WHERE first_name = $1")
+            .bind(&names[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,7 @@
+mod register_user;
+mod user_session;
+mod verify_user_credentials;
+
+pub use register_user::*;
+pub use user_session::*;
+pub use verify_user_credentials::*;
@@ -0,0 +1,56 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, User, UserRegistration};
+use crate::utils::hash_password;
+use anyhow::anyhow;
+use sqlx::PgConnection;
+use validator::Validate;
+
+/// Creates a user who can log in with an email and password.
+///
+/// Runs on a connection acting as the tenant, the row is given the tenant's id like any other.
+/// An email already taken within the tenant is a conflict.
+#[tracing::instrument(name = "Registering user.", skip_all)]
+pub async fn register_user(
+    connection: &mut PgConnection,
+    registration: &UserRegistration,
+    configuration: &Configuration,
+) -> Result<User, AppError> {
+    registration
+        .validate()
+        .map_err(|e| AppError::BadRequestError(e.to_string()))?;
+
+    let password_hash = {
+        let password = registration.password.clone();
+        let pepper = configuration.secrets.current().argon2_key.clone();
+
+        tokio::task::spawn_blocking(move || hash_password(&password, &pepper))
+            .await
+            .map_err(|e| AppError::InternalError(anyhow!(e)))?
+            .map_err(|e| AppError::InternalError(anyhow!(e)))?
+    };
+
+    sqlx::query_as::<_, User>(
+        r#"
+        INSERT INTO users (first_name, last_name, email, password_hash)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(&registration.first_name)
+    .bind(&registration.last_name)
+    .bind(normalize_email(&registration.email))
+    .bind(password_hash)
+    .fetch_one(connection)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(database_error) if database_error.is_unique_violation() => {
+            AppError::ConflictError("Email is already registered".to_string())
+        }
+        _ => AppError::DatabaseError(e),
+    })
+}
+
+/// Emails are stored and looked up trimmed and lowercased.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
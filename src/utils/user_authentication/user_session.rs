@@ -0,0 +1,129 @@
+use crate::configurations::Configuration;
+use crate::models::AppError;
+use actix_session::{Session, SessionExt};
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use anyhow::anyhow;
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+pub const USER_ID_SESSION_KEY: &str = "user_id";
+
+/// The logged in user of a request and the tenant they logged in to.
+///
+/// The tenant id is stored under `tenant_resolution.session_key`, where the `session` tenant
+/// resolution strategy looks for it. Extracting it from a request without a login is a `401`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UserSession {
+    pub user_id: Uuid,
+    pub tenant_id: Uuid,
+}
+
+impl UserSession {
+    /// Stores the login in the session, under a new session key to prevent session fixation.
+    pub fn start(&self, session: &Session, configuration: &Configuration) -> Result<(), AppError> {
+        session.renew();
+        session
+            .insert(USER_ID_SESSION_KEY, self.user_id)
+            .and_then(|_| {
+                session.insert(&configuration.tenant_resolution.session_key, self.tenant_id)
+            })
+            .map_err(|e| AppError::InternalError(anyhow!("Failed to store the session: {}", e)))
+    }
+
+    pub fn end(session: &Session) {
+        session.purge();
+    }
+
+    fn from_session(session: &Session, configuration: &Configuration) -> Result<Self, AppError> {
+        let user_id = session.get::<Uuid>(USER_ID_SESSION_KEY);
+        let tenant_id = session.get::<Uuid>(&configuration.tenant_resolution.session_key);
+
+        match (user_id, tenant_id) {
+            (Ok(Some(user_id)), Ok(Some(tenant_id))) => Ok(UserSession { user_id, tenant_id }),
+            _ => Err(AppError::AuthenticationFailed(
+                "No user is logged in".to_string(),
+            )),
+        }
+    }
+}
+
+impl FromRequest for UserSession {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let result = match req.app_data::<web::Data<Configuration>>() {
+            Some(configuration) => UserSession::from_session(&req.get_session(), configuration),
+            None => Err(AppError::InternalError(anyhow!(
+                "Configuration is missing from the application data"
+            ))),
+        };
+
+        ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use actix_session::storage::CookieSessionStore;
+    use actix_session::SessionMiddleware;
+    use actix_web::cookie::Key;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_user_session() {
+        let user_session = UserSession {
+            user_id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(
+                    get_configuration().expect("Failed to read configuration."),
+                ))
+                .wrap(
+                    SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                        .cookie_secure(false)
+                        .build(),
+                )
+                .route(
+                    "/login",
+                    web::post().to(
+                        move |session: Session, configuration: web::Data<Configuration>| async move {
+                            user_session.start(&session, &configuration)?;
+                            Ok::<_, AppError>(HttpResponse::Ok().finish())
+                        },
+                    ),
+                )
+                .route(
+                    "/me",
+                    web::get().to(|user_session: UserSession| async move {
+                        HttpResponse::Ok().json(user_session.user_id)
+                    }),
+                ),
+        )
+        .await;
+
+        let anonymous =
+            test::call_service(&app, test::TestRequest::get().uri("/me").to_request()).await;
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+        let login =
+            test::call_service(&app, test::TestRequest::post().uri("/login").to_request()).await;
+        let cookie = login.response().cookies().next().unwrap().into_owned();
+
+        let me: Uuid = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get()
+                .uri("/me")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(me, user_session.user_id);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::configurations::Configuration;
+use crate::models::{AppError, User, UserCredentials};
+use crate::utils::{generate_password, hash_password, normalize_email, verify_password};
+use anyhow::anyhow;
+use secrecy::SecretString;
+use sqlx::{FromRow, PgConnection};
+use std::sync::OnceLock;
+
+#[derive(FromRow)]
+struct UserCredentialsRow {
+    #[sqlx(flatten)]
+    user: User,
+    password_hash: Option<String>,
+}
+
+/// Checks an email and password against the tenant's users.
+///
+/// Unknown emails and users without a password are checked against a placeholder hash, so every
+/// failure costs one Argon2 verification and is reported as the same authentication error.
+#[tracing::instrument(name = "Verifying user credentials.", skip_all)]
+pub async fn verify_user_credentials(
+    connection: &mut PgConnection,
+    credentials: &UserCredentials,
+    configuration: &Configuration,
+) -> Result<User, AppError> {
+    let row = sqlx::query_as::<_, UserCredentialsRow>("SELECT * FROM users WHERE email = $1")
+        .bind(normalize_email(&credentials.email))
+        .fetch_optional(connection)
+        .await?;
+
+    let (user, password_hash) = match row {
+        Some(UserCredentialsRow {
+            user,
+            password_hash: Some(password_hash),
+        }) => (Some(user), Some(password_hash)),
+        _ => (None, None),
+    };
+
+    let verified = {
+        let password = credentials.password.clone();
+        let pepper = configuration.secrets.current().argon2_key.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let password_hash = match &password_hash {
+                Some(password_hash) => password_hash.as_str(),
+                None => placeholder_password_hash(),
+            };
+
+            verify_password(&password, password_hash, &pepper)
+        })
+        .await
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?
+        .map_err(|e| AppError::InternalError(anyhow!(e)))?
+    };
+
+    match user {
+        Some(user) if verified => Ok(user),
+        _ => Err(AppError::AuthenticationFailed(
+            "Invalid email or password".to_string(),
+        )),
+    }
+}
+
+// Hashed with the default parameters, like the stored hashes, so verifying it takes as long.
+// Nothing hashes to it, any password fails.
+fn placeholder_password_hash() -> &'static str {
+    static PLACEHOLDER_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
+
+    PLACEHOLDER_PASSWORD_HASH.get_or_init(|| {
+        let password = generate_password();
+
+        hash_password(&password, &SecretString::default())
+            .expect("Hashing the placeholder password failed.")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configurations::get_configuration;
+    use crate::models::{AppState, TenantPoolRegistry, UserRegistration};
+    use crate::utils::{begin_tenant_transaction, register_user};
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use secrecy::SecretString;
+    use sqlx::postgres::PgPoolOptions;
+    use uuid::Uuid;
+
+    // Runs against the development database: `cargo test -- --ignored`
+    #[actix_web::test]
+    #[ignore = "requires a migrated database"]
+    async fn test_verify_user_credentials() {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let pool = PgPoolOptions::new()
+            .connect_with(configuration.database.with_db())
+            .await
+            .expect("Failed to connect to the database.");
+        let tenant_id: Uuid = sqlx::query_scalar(
+            "SELECT id FROM tenants WHERE status = 'active' ORDER BY created_at LIMIT 1",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Expected at least one active tenant.");
+        let state = AppState {
+            pools: TenantPoolRegistry::new(),
+            shared_pool: None,
+        };
+
+        // Rolled back when dropped, the user is never stored
+        let mut transaction = begin_tenant_transaction(&tenant_id, &state, &pool, &configuration)
+            .await
+            .unwrap();
+        let email = format!("{}@example.com", Uuid::new_v4());
+        let user = register_user(
+            &mut transaction,
+            &UserRegistration {
+                first_name: "Ada".to_string(),
+                last_name: "Lovelace".to_string(),
+                email: email.to_uppercase(),
+                password: SecretString::from("correct horse battery"),
+            },
+            &configuration,
+        )
+        .await
+        .unwrap();
+
+        let credentials = |email: &str, password: &str| UserCredentials {
+            email: email.to_string(),
+            password: SecretString::from(password),
+        };
+
+        let verified = verify_user_credentials(
+            &mut transaction,
+            &credentials(&email, "correct horse battery"),
+            &configuration,
+        )
+        .await
+        .unwrap();
+        assert_eq!(verified.id, user.id);
+        assert_eq!(verified.email.as_deref(), Some(email.as_str()));
+
+        for (email, password) in [
+            (email.as_str(), "wrong horse battery"),
+            ("nobody@example.com", "correct horse battery"),
+        ] {
+            let error = verify_user_credentials(
+                &mut transaction,
+                &credentials(email, password),
+                &configuration,
+            )
+            .await
+            .err()
+            .unwrap();
+
+            assert_eq!(error.status_code(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}